@@ -0,0 +1,133 @@
+//! Throughput of the line matcher, the diff parser and the syntect comment extraction, on the Rust
+//! fixture repeated into a larger input.
+
+use std::path::Path;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use regex::RegexBuilder;
+use report_todo::{
+    checkers::{diff::UnifiedDiffParser, source_tree_syntect::CommentScopeStack},
+    todo_error::{CaptureGroups, IssuePattern, Regexes, TodoError, ALLOW_MARKER},
+};
+use span::Span;
+
+const FIXTURE: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/test_cases/rust.rs"));
+
+const REPEAT: usize = 100;
+
+fn source() -> String {
+    FIXTURE.repeat(REPEAT)
+}
+
+/// The default configuration: `TODO` and `FIXME` tracked as `TODO(#123):`.
+fn regexes() -> Regexes {
+    let case_insensitive = |pattern: &str| {
+        RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .unwrap()
+    };
+    Regexes {
+        match_issues: vec![IssuePattern {
+            regex: case_insensitive(r"\btodo\(#(?P<issue_number>\d+)\):"),
+            link_format: None,
+        }],
+        match_issue_url: None,
+        bad_keywords: vec![
+            case_insensitive(r"\btodo\b"),
+            case_insensitive(r"\bfixme\b"),
+        ],
+        warning_keywords: Vec::new(),
+        allow_marker: case_insensitive(ALLOW_MARKER),
+        match_wrapped: false,
+        first_match_only: false,
+        groups: CaptureGroups::default(),
+        rust_macros: None,
+        languages: Vec::new(),
+        prefilter: None,
+    }
+}
+
+fn from_line(c: &mut Criterion) {
+    let config = regexes();
+    let source = source();
+
+    let mut group = c.benchmark_group("TodoError::from_line");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.bench_function("rust.rs", |b| {
+        b.iter(|| {
+            source
+                .lines()
+                .enumerate()
+                .map(|(index, line)| {
+                    TodoError::from_line(&config, Path::new("rust.rs"), line, index + 1).len()
+                })
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+fn unified_diff_parser(c: &mut Criterion) {
+    // Every line of the fixture added, in one hunk per file.
+    let diff = (0..REPEAT)
+        .map(|file| {
+            let added = FIXTURE
+                .lines()
+                .map(|line| format!("+{}\n", line))
+                .collect::<String>();
+            format!(
+                "diff --git a/{0}.rs b/{0}.rs\n--- /dev/null\n+++ b/{0}.rs\n@@ -0,0 +1,{1} @@\n{2}",
+                file,
+                FIXTURE.lines().count(),
+                added
+            )
+        })
+        .collect::<String>();
+
+    let mut group = c.benchmark_group("UnifiedDiffParser");
+    group.throughput(Throughput::Bytes(diff.len() as u64));
+    group.bench_function("hunks", |b| {
+        b.iter(|| {
+            let mut parser = UnifiedDiffParser::new(black_box(&diff)).unwrap();
+            let mut hunks = 0;
+            while parser.has_more() {
+                black_box(parser.read_hunk().unwrap());
+                hunks += 1;
+            }
+            hunks
+        })
+    });
+    group.finish();
+}
+
+fn comment_scope_stack(c: &mut Criterion) {
+    let syntax_set = syntect::parsing::SyntaxSet::load_defaults_newlines();
+    let syntax = syntax_set.find_syntax_by_extension("rs").unwrap();
+    let source = source();
+
+    let mut group = c.benchmark_group("CommentScopeStack");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.sample_size(10);
+    group.bench_function("rust.rs", |b| {
+        b.iter(|| {
+            let mut state = syntect::parsing::ParseState::new(syntax);
+            let file_span = Span::new(&source, 0, source.len()).unwrap();
+            let mut stack = CommentScopeStack::new(file_span.clone());
+            let mut comments = 0;
+            for line in file_span.lines_span() {
+                comments += stack
+                    .process_ops_for_line(
+                        state.parse_line(line.as_str(), &syntax_set).into_iter(),
+                        line,
+                    )
+                    .len();
+            }
+            comments
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, from_line, unified_diff_parser, comment_scope_stack);
+criterion_main!(benches);
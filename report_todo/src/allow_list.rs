@@ -0,0 +1,158 @@
+//! Tolerating untracked findings at listed locations, from a `.todo-allow` file.
+
+use std::{path::Path, str::FromStr};
+
+use anyhow::{anyhow, Context};
+
+use crate::paths::display_path;
+
+/// Name of the allow list, looked for in the root directory.
+pub const ALLOW_LIST_FILE_NAME: &str = ".todo-allow";
+
+/// A file, or a line in a file, where untracked findings are tolerated. Parsed from `path` or
+/// `path:line`, with `path` relative to the root directory.
+#[derive(Debug, Clone, PartialEq)]
+struct AllowEntry {
+    path: String,
+    line: Option<usize>,
+}
+
+impl FromStr for AllowEntry {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, line) = match s.rsplit_once(':') {
+            Some((path, line)) if line.bytes().all(|b| b.is_ascii_digit()) => {
+                let line = line
+                    .parse::<usize>()
+                    .with_context(|| format!("invalid line in `{}`", s))?;
+                (path, Some(line))
+            }
+            _ => (s, None),
+        };
+        let path = path.strip_prefix("./").unwrap_or(path);
+        if path.is_empty() {
+            return Err(anyhow!("missing path in `{}`", s));
+        }
+        Ok(AllowEntry {
+            path: path.replace('\\', "/"),
+            line,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AllowList {
+    entries: Vec<AllowEntry>,
+
+    /// Whether each entry has allowed a finding, for reporting stale entries.
+    used: Vec<bool>,
+}
+
+impl AllowList {
+    /// Reads `.todo-allow` from `root_dir`, if there is one.
+    pub fn load(root_dir: &Path) -> anyhow::Result<Option<Self>> {
+        let path = root_dir.join(ALLOW_LIST_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        contents
+            .parse()
+            .map(Some)
+            .with_context(|| format!("invalid {}", path.display()))
+    }
+
+    /// Whether an untracked finding at `row` of `file_path` is allowed. `file_path` is taken
+    /// relative to `root_dir`.
+    pub fn allows(&mut self, root_dir: &Path, file_path: &Path, row: usize) -> bool {
+        let path = relative_path(root_dir, file_path);
+        let mut allowed = false;
+        for (entry, used) in self.entries.iter().zip(&mut self.used) {
+            if entry.path == path && entry.line.is_none_or(|line| line == row) {
+                *used = true;
+                allowed = true;
+            }
+        }
+        allowed
+    }
+
+    /// Entries that allowed no finding, as written in the file.
+    pub fn stale(&self) -> impl Iterator<Item = String> + '_ {
+        self.entries
+            .iter()
+            .zip(&self.used)
+            .filter(|(_, used)| !**used)
+            .map(|(entry, _)| match entry.line {
+                Some(line) => format!("{}:{}", entry.path, line),
+                None => entry.path.clone(),
+            })
+    }
+}
+
+impl FromStr for AllowList {
+    type Err = anyhow::Error;
+
+    /// One entry per line. Blank lines and lines starting with `#` are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let entries = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::parse)
+            .collect::<anyhow::Result<Vec<AllowEntry>>>()?;
+        Ok(AllowList {
+            used: vec![false; entries.len()],
+            entries,
+        })
+    }
+}
+
+/// `file_path` as `/`-separated text relative to `root_dir`, if it's below it.
+fn relative_path(root_dir: &Path, file_path: &Path) -> String {
+    let path = file_path.strip_prefix(root_dir).unwrap_or(file_path);
+    let path = display_path(path);
+    path.strip_prefix("./").unwrap_or(&path).to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_entries() {
+        let mut allow_list = "# prototypes\n\nscripts/spike.py\n./tools/gen.sh\n"
+            .parse::<AllowList>()
+            .unwrap();
+        let root = Path::new(".");
+
+        assert!(allow_list.allows(root, Path::new("./scripts/spike.py"), 1));
+        assert!(allow_list.allows(root, Path::new("./scripts/spike.py"), 40));
+        assert!(!allow_list.allows(root, Path::new("./scripts/other.py"), 1));
+        assert_eq!(allow_list.stale().collect::<Vec<_>>(), vec!["tools/gen.sh"]);
+
+        let mut allow_list = "src/a.rs".parse::<AllowList>().unwrap();
+        assert!(allow_list.allows(Path::new("repo"), Path::new("repo/src/a.rs"), 3));
+    }
+
+    #[test]
+    fn path_line_entries() {
+        let mut allow_list = "src/a.rs:12\nsrc/a.rs:30\n".parse::<AllowList>().unwrap();
+        let root = Path::new(".");
+
+        assert!(allow_list.allows(root, Path::new("./src/a.rs"), 12));
+        assert!(!allow_list.allows(root, Path::new("./src/a.rs"), 13));
+        assert!(!allow_list.allows(root, Path::new("./src/b.rs"), 12));
+        assert_eq!(allow_list.stale().collect::<Vec<_>>(), vec!["src/a.rs:30"]);
+
+        assert_eq!(
+            "C:odd:7".parse::<AllowEntry>().unwrap(),
+            AllowEntry {
+                path: "C:odd".to_owned(),
+                line: Some(7)
+            }
+        );
+        assert!(":7".parse::<AllowEntry>().is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! When lines were last changed, according to `git blame`.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use log::debug;
+
+/// Commit times of each line, blamed a file at a time and cached.
+#[derive(Debug, Default)]
+pub struct Blame {
+    /// Unix commit time of each line, by row. `None` if the file couldn't be blamed, e.g. because
+    /// it isn't tracked by git.
+    files: HashMap<PathBuf, Option<Vec<u64>>>,
+}
+
+impl Blame {
+    /// The commit time of the line at `row` (1-based) of `path`, or `None` if it isn't committed.
+    pub fn line_time(&mut self, path: &Path, row: usize) -> Option<u64> {
+        self.files
+            .entry(path.to_owned())
+            .or_insert_with(|| blame_file(path))
+            .as_ref()?
+            .get(row.checked_sub(1)?)
+            .copied()
+            .filter(|&time| time != UNCOMMITTED)
+    }
+}
+
+/// Commit time of `git_ref`, e.g. a release tag.
+pub fn ref_time(git_ref: &str) -> anyhow::Result<u64> {
+    debug!("Running `git log -1 --format=%ct {}`", git_ref);
+    let output = duct::cmd!("git", "log", "-1", "--format=%ct", git_ref, "--")
+        .stderr_null()
+        .read()
+        .with_context(|| format!("failed to find the commit for `{}`", git_ref))?;
+    output
+        .trim()
+        .parse()
+        .with_context(|| format!("unexpected commit time for `{}`: {}", git_ref, output))
+}
+
+/// Marks lines `git blame` attributes to the working tree rather than a commit.
+const UNCOMMITTED: u64 = u64::MAX;
+
+fn blame_file(path: &Path) -> Option<Vec<u64>> {
+    debug!("Running `git blame --line-porcelain {}`", path.display());
+    let output = duct::cmd!("git", "blame", "--line-porcelain", "--", path)
+        .stderr_null()
+        .read()
+        .ok()?;
+    Some(parse_line_porcelain(&output))
+}
+
+/// Reads the committer time of each line from `git blame --line-porcelain` output.
+fn parse_line_porcelain(output: &str) -> Vec<u64> {
+    let mut times = Vec::new();
+    let mut uncommitted = false;
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            continue;
+        }
+
+        if let Some(commit) = line.split(' ').next() {
+            if commit.len() == 40 && commit.bytes().all(|b| b.is_ascii_hexdigit()) {
+                uncommitted = commit.bytes().all(|b| b == b'0');
+                continue;
+            }
+        }
+
+        if let Some(time) = line.strip_prefix("committer-time ") {
+            times.push(if uncommitted {
+                UNCOMMITTED
+            } else {
+                time.parse().unwrap_or(UNCOMMITTED)
+            });
+        }
+    }
+    times
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_porcelain() {
+        let output = "\
+1111111111111111111111111111111111111111 1 1 1
+author A
+committer-time 100
+filename a.rs
+\t// TODO: old
+0000000000000000000000000000000000000000 2 2 1
+author Not Committed Yet
+committer-time 300
+filename a.rs
+\t// TODO: new
+1111111111111111111111111111111111111111 3 3
+author A
+committer-time 100
+filename a.rs
+\tcommitter-time 5
+";
+
+        assert_eq!(parse_line_porcelain(output), vec![100, UNCOMMITTED, 100]);
+    }
+}
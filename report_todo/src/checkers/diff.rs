@@ -0,0 +1,722 @@
+//! Find TODOs on the lines added by a unified diff.
+
+use std::{
+    collections::HashSet,
+    iter::Peekable,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::Lines,
+};
+
+use anyhow::{anyhow, Context};
+use log::debug;
+
+use crate::{
+    language::Language,
+    paths::from_repo_relative,
+    todo_error::{Regexes, TodoError},
+};
+
+use super::{Checker, ScanStats, SkipFiles};
+
+/// Scans a diff that has already been produced, by git or any other version control system.
+pub struct DiffChecker {
+    /// The diff, in unified format.
+    pub diff: String,
+
+    /// The directory the diff's paths are relative to, e.g. `..` for a git diff run in a
+    /// subdirectory of the repository.
+    pub root: PathBuf,
+
+    /// Also report tracked findings on removed lines, marked as removed.
+    pub report_removed: bool,
+
+    pub skip: SkipFiles,
+}
+
+impl Checker for DiffChecker {
+    fn process_spans(&self, config: &Regexes, stats: &ScanStats) -> anyhow::Result<Vec<TodoError>> {
+        scan_diff(
+            &self.diff,
+            &self.root,
+            config,
+            self.report_removed,
+            &self.skip,
+            stats,
+        )
+    }
+}
+
+/// Finds the TODOs on the lines added in `diff` (and the tracked ones on removed lines, with
+/// `report_removed`), sorted by location so that the order doesn't depend on git's (configurable)
+/// file order.
+fn scan_diff(
+    diff: &str,
+    root: &Path,
+    config: &Regexes,
+    report_removed: bool,
+    skip: &SkipFiles,
+    stats: &ScanStats,
+) -> anyhow::Result<Vec<TodoError>> {
+    let mut patch = UnifiedDiffParser::new(diff)?;
+
+    let mut todo_errors = Vec::new();
+    let mut current_file = None;
+    let mut language = None;
+    let mut skipped = HashSet::new();
+
+    loop {
+        if !patch.has_more() {
+            break;
+        }
+
+        let hunk = patch.read_hunk()?;
+        let path = from_repo_relative(root, hunk.file);
+        if skipped.contains(hunk.file) {
+            continue;
+        }
+        if current_file != Some(hunk.file) && skip.skips_file(&path) {
+            debug!("skipping {}", path.display());
+            skipped.insert(hunk.file);
+            continue;
+        }
+
+        if current_file != Some(hunk.file) {
+            current_file = Some(hunk.file);
+            language = Language::detect(&path, &config.languages);
+            stats.record_file(&path, 0);
+        }
+        stats.record_bytes(hunk.added.iter().map(|line| line.line.len()).sum());
+        // Only an added line can be seen to be right before another; context lines aren't kept.
+        let mut previous: Option<&ChangedLine> = None;
+        for line in &hunk.added {
+            let previous_line = previous
+                .filter(|previous| previous.row + 1 == line.row)
+                .map(|previous| previous.line);
+            todo_errors.extend(TodoError::from_line_after(
+                config,
+                &path,
+                language.as_ref(),
+                previous_line,
+                line.line,
+                line.row,
+            ));
+            previous = Some(line);
+        }
+        if report_removed {
+            // Rows of removed lines are in the original file, which may have been renamed.
+            let source_path = from_repo_relative(root, hunk.source_file);
+            let source_language = Language::detect(&source_path, &config.languages);
+            for line in &hunk.removed {
+                todo_errors.extend(
+                    TodoError::from_line_in(
+                        config,
+                        &source_path,
+                        source_language.as_ref(),
+                        line.line,
+                        line.row,
+                    )
+                    .into_iter()
+                    .filter(TodoError::is_tracked)
+                    .map(|mut todo_error| {
+                        todo_error.mark_removed();
+                        todo_error
+                    }),
+                );
+            }
+        }
+    }
+
+    todo_errors
+        .sort_by(|a, b| (a.file_path(), a.row(), a.col()).cmp(&(b.file_path(), b.row(), b.col())));
+    Ok(todo_errors)
+}
+
+#[derive(Debug)]
+pub struct UnifiedDiffParser<'a> {
+    source: &'a str,
+
+    lines: Peekable<Lines<'a>>,
+
+    current_file: &'a str,
+    current_source_file: &'a str,
+    current_patch_remove: Range<usize>,
+    current_patch_add: Range<usize>,
+}
+
+impl<'a> UnifiedDiffParser<'a> {
+    pub fn new(source: &'a str) -> anyhow::Result<Self> {
+        let mut parser = UnifiedDiffParser {
+            source,
+            lines: source.lines().peekable(),
+            current_file: "",
+            current_source_file: "",
+            current_patch_remove: Range { start: 0, end: 0 },
+            current_patch_add: Range { start: 0, end: 0 },
+        };
+
+        parser.eat_file_header()?;
+
+        Ok(parser)
+    }
+
+    pub fn has_more(&mut self) -> bool {
+        self.lines.peek().is_some()
+    }
+
+    /// Reads the next file's `--- `/`+++ ` header. Running out of lines first isn't an error: the
+    /// diff may be empty, or the remaining files binary, renamed or with only their mode changed,
+    /// which git lists without a header.
+    fn eat_file_header(&mut self) -> anyhow::Result<()> {
+        // Advance to the file indicator marker(s).
+        loop {
+            match self.lines.peek() {
+                None => return Ok(()),
+                Some(line) if line.starts_with("--- ") || line.starts_with("+++ ") => break,
+                // ignore line
+                Some(_) => {
+                    self.lines.next().unwrap();
+                }
+            }
+        }
+
+        let source_file_line = self.lines.next().context("source line exists")?;
+        if !source_file_line.starts_with("--- ") {
+            return Err(anyhow!("remove line invalid: {}", source_file_line));
+        }
+
+        let target_file_line = self.lines.next().context("target line exists")?;
+        if !target_file_line.starts_with("+++ ") {
+            return Err(anyhow!("add line invalid: {}", target_file_line));
+        }
+
+        self.current_source_file = header_path(source_file_line, "a/");
+        self.current_file = header_path(target_file_line, "b/");
+        debug!("Read added file: {}", self.current_file);
+
+        Ok(())
+    }
+
+    pub fn read_hunk(&mut self) -> anyhow::Result<Hunk<'a>> {
+        // @@ -26,0 +27,6 @@ dependencies = [
+        let line = self
+            .lines
+            .next()
+            .context("before reading hunk: next line exists")?;
+
+        let mut parts = line
+            .strip_prefix("@@ ")
+            .context(anyhow!("patch line invalid: {}", line))?
+            .split(" ");
+
+        let current_patch_remove = {
+            let removed = parts
+                .next()
+                .context(anyhow!("patch missing removed section: {}", line))?
+                .strip_prefix("-")
+                .context(anyhow!("patch missing removed section: {}", line))?;
+            let mut parts = removed.split(",");
+            let removed_row: usize = parts
+                .next()
+                .context("missing row")?
+                .parse()
+                .context("failed to parse row")?;
+            let removed_len: usize = parts
+                .next()
+                .context("missing removed length")
+                .and_then(|l| l.parse().context("failed to parse length"))
+                .unwrap_or(1);
+
+            Range {
+                start: removed_row,
+                end: removed_row + removed_len,
+            }
+        };
+
+        let current_patch_add = {
+            let added = parts
+                .next()
+                .context(anyhow!("patch missing added section: {}", line))?
+                .strip_prefix("+")
+                .context(anyhow!("patch missing added section: {}", line))?;
+            let mut parts = added.split(",");
+            let added_row: usize = parts
+                .next()
+                .context("missing row")?
+                .parse()
+                .context("failed to parse row")?;
+            let added_len: usize = parts
+                .next()
+                .context("missing added length")
+                .and_then(|l| l.parse().context("failed to parse length"))
+                .unwrap_or(1);
+
+            Range {
+                start: added_row,
+                end: added_row + added_len,
+            }
+        };
+
+        let mut hunk = Hunk {
+            file: self.current_file,
+            source_file: self.current_source_file,
+            removed: Vec::new(),
+            added: Vec::new(),
+        };
+
+        // Without `--unified=0`, removed and added lines are interleaved with context lines.
+        let mut removed_row = current_patch_remove.start;
+        let mut added_row = current_patch_add.start;
+        while removed_row < current_patch_remove.end || added_row < current_patch_add.end {
+            let line = self.lines.next().context("hunk is missing lines")?;
+            if let Some(removed) = line.strip_prefix('-') {
+                hunk.removed.push(ChangedLine {
+                    line: removed.trim_end(),
+                    row: removed_row,
+                });
+                removed_row += 1;
+            } else if let Some(added) = line.strip_prefix('+') {
+                hunk.added.push(ChangedLine {
+                    line: added.trim_end(),
+                    row: added_row,
+                });
+                added_row += 1;
+            } else if line.starts_with('\\') {
+                // A `\ No newline at end of file` marker, which git emits between the removed and
+                // added lines when either side lacks a trailing newline.
+            } else {
+                removed_row += 1;
+                added_row += 1;
+            }
+        }
+
+        // Advance to the next file or next hunk. Plain `diff -u` output has no `diff ` line
+        // before each file's header.
+        loop {
+            if let Some(line) = self.lines.peek() {
+                if line.starts_with("diff ") || line.starts_with("--- ") {
+                    self.eat_file_header()?;
+                    break;
+                } else if line.starts_with("@@ ") {
+                    break;
+                } else {
+                    // ignore line
+                    self.lines.next().context("no more patch!")?;
+                }
+            } else {
+                // end of file
+                break;
+            }
+        }
+
+        Ok(hunk)
+    }
+}
+
+/// The path in a `--- ` or `+++ ` line: without git's `a/` or `b/` prefix, or the timestamp that
+/// `diff -u` and Mercurial add after a tab.
+fn header_path<'a>(file_line: &'a str, git_prefix: &str) -> &'a str {
+    let path = &file_line["+++ ".len()..];
+    let path = path.split('\t').next().unwrap_or(path);
+    path.strip_prefix(git_prefix).unwrap_or(path)
+}
+
+#[derive(Debug)]
+pub struct Hunk<'a> {
+    file: &'a str,
+
+    /// The file before the change, which differs from `file` when it was renamed or added.
+    source_file: &'a str,
+
+    removed: Vec<ChangedLine<'a>>,
+    added: Vec<ChangedLine<'a>>,
+}
+
+#[derive(Debug)]
+pub struct ChangedLine<'a> {
+    line: &'a str,
+    row: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::display_path;
+
+    /// `(file, removed rows, added (row, line) pairs)`
+    type HunkSummary<'a> = (&'a str, Vec<usize>, Vec<(usize, &'a str)>);
+
+    fn parse_hunks(diff: &str) -> Vec<HunkSummary<'_>> {
+        let mut patch = UnifiedDiffParser::new(diff).unwrap();
+        let mut hunks = Vec::new();
+        while patch.has_more() {
+            let hunk = patch.read_hunk().unwrap();
+            hunks.push((
+                hunk.file,
+                hunk.removed.iter().map(|line| line.row).collect(),
+                hunk.added
+                    .iter()
+                    .map(|line| (line.row, line.line))
+                    .collect(),
+            ));
+        }
+        hunks
+    }
+
+    #[test]
+    fn single_file_three_hunks() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,0 +2 @@ fn main() {
++    // TODO: first
+@@ -10 +11 @@ fn foo() {
+-    old();
++    // TODO: second
+@@ -20,2 +21,2 @@ fn bar() {
+-    gone();
+-    gone_too();
++    // TODO: third
++    new();
+";
+
+        assert_eq!(
+            parse_hunks(diff),
+            vec![
+                ("src/lib.rs", vec![], vec![(2, "    // TODO: first")]),
+                ("src/lib.rs", vec![10], vec![(11, "    // TODO: second")]),
+                (
+                    "src/lib.rs",
+                    vec![20, 21],
+                    vec![(21, "    // TODO: third"), (22, "    new();")]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_diff() {
+        assert_eq!(parse_hunks(""), vec![]);
+
+        // As read from an empty `--diff-stdin`.
+        let checker = DiffChecker {
+            diff: String::new(),
+            root: PathBuf::from("."),
+            report_removed: true,
+            skip: SkipFiles::default(),
+        };
+        assert!(checker
+            .process_spans(&regexes(), &ScanStats::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn truncated_hunk_header() {
+        for header in &["@@ -1", "@@ -1 +", "@@ -1 +x @@", "@@ - +1 @@"] {
+            let checker = DiffChecker {
+                diff: format!("--- a/x.rs\n+++ b/x.rs\n{}\n+// TODO: a\n", header),
+                root: PathBuf::from("."),
+                report_removed: true,
+                skip: SkipFiles::default(),
+            };
+            // An error, rather than a panic, so the run exits with code 2.
+            assert!(
+                checker
+                    .process_spans(&regexes(), &ScanStats::default())
+                    .is_err(),
+                "{}",
+                header
+            );
+        }
+    }
+
+    #[test]
+    fn files_without_hunks() {
+        let diff = "\
+diff --git a/logo.png b/logo.png
+index 1111111..2222222 100644
+Binary files a/logo.png and b/logo.png differ
+diff --git a/old.rs b/new.rs
+similarity index 100%
+rename from old.rs
+rename to new.rs
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+";
+        assert_eq!(parse_hunks(diff), vec![]);
+        assert_eq!(locations(diff), vec![]);
+
+        let diff = "\
+diff --git a/a.rs b/a.rs
+index 1111111..2222222 100644
+--- a/a.rs
++++ b/a.rs
+@@ -3,0 +4 @@
++// TODO: a
+diff --git a/logo.png b/logo.png
+index 1111111..2222222 100644
+Binary files a/logo.png and b/logo.png differ
+diff --git a/b.rs b/b.rs
+index 3333333..4444444 100644
+--- a/b.rs
++++ b/b.rs
+@@ -1,0 +2 @@
++// TODO: b
+diff --git a/old.rs b/new.rs
+similarity index 100%
+rename from old.rs
+rename to new.rs
+";
+        assert_eq!(
+            parse_hunks(diff),
+            vec![
+                ("a.rs", vec![], vec![(4, "// TODO: a")]),
+                ("b.rs", vec![], vec![(2, "// TODO: b")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn two_files_two_hunks_each() {
+        let diff = "\
+diff --git a/a.rs b/a.rs
+index 1111111..2222222 100644
+--- a/a.rs
++++ b/a.rs
+@@ -3,0 +4 @@
++// TODO: a1
+@@ -8 +9,0 @@
+-removed();
+diff --git a/b.rs b/b.rs
+index 3333333..4444444 100644
+--- a/b.rs
++++ b/b.rs
+@@ -1 +1 @@
+-// TODO(#1): b1
++// TODO: b1
+@@ -5,0 +6,2 @@
++// TODO: b2
++// TODO: b3
+";
+
+        assert_eq!(
+            parse_hunks(diff),
+            vec![
+                ("a.rs", vec![], vec![(4, "// TODO: a1")]),
+                ("a.rs", vec![8], vec![]),
+                ("b.rs", vec![1], vec![(1, "// TODO: b1")]),
+                ("b.rs", vec![], vec![(6, "// TODO: b2"), (7, "// TODO: b3")]),
+            ]
+        );
+    }
+
+    fn regexes() -> Regexes {
+        Regexes::for_tests(r"TODO\(#(?P<issue_number>\d+)\)")
+    }
+
+    fn locations(diff: &str) -> Vec<(String, usize, usize)> {
+        scan_diff(
+            diff,
+            Path::new("."),
+            &regexes(),
+            false,
+            &SkipFiles::default(),
+            &ScanStats::default(),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|todo_error| {
+            (
+                display_path(todo_error.file_path()),
+                todo_error.row(),
+                todo_error.col(),
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn findings_are_sorted_by_location() {
+        let diff = "\
+diff --git a/b.rs b/b.rs
+index 3333333..4444444 100644
+--- a/b.rs
++++ b/b.rs
+@@ -5,0 +6 @@
++// TODO: b6
+@@ -1,0 +2 @@
++let x = 1; // TODO: b2
+diff --git a/a.rs b/a.rs
+index 1111111..2222222 100644
+--- a/a.rs
++++ b/a.rs
+@@ -3,0 +4 @@
++// TODO: a4
+";
+
+        assert_eq!(
+            locations(diff),
+            vec![
+                ("./a.rs".to_owned(), 4, 4),
+                ("./b.rs".to_owned(), 2, 15),
+                ("./b.rs".to_owned(), 6, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn indented_findings_in_two_hunks() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3,0 +4,2 @@ fn main() {
++    let x = 1;
++    // TODO: first
+@@ -40 +42 @@ impl Foo {
+-        old();
++\t\t// TODO: second
+";
+
+        // Columns count from the start of the line in the file, without the diff's `+`.
+        assert_eq!(
+            locations(diff),
+            vec![
+                ("./src/lib.rs".to_owned(), 5, 8),
+                ("./src/lib.rs".to_owned(), 42, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_tracked_findings() {
+        let diff = "\
+diff --git a/old.rs b/new.rs
+similarity index 90%
+rename from old.rs
+rename to new.rs
+index 1111111..2222222 100644
+--- a/old.rs
++++ b/new.rs
+@@ -3,2 +3 @@ fn main() {
+-    // TODO(#42): remove this workaround
+-    // TODO: untracked, so not reported
++    workaround();
+";
+        let todo_errors = scan_diff(
+            diff,
+            Path::new("."),
+            &regexes(),
+            true,
+            &SkipFiles::default(),
+            &ScanStats::default(),
+        )
+        .unwrap();
+
+        assert_eq!(todo_errors.len(), 1);
+        let removed = &todo_errors[0];
+        assert!(removed.is_removed());
+        assert_eq!(display_path(removed.file_path()), "./old.rs");
+        assert_eq!(removed.row(), 3);
+        assert_eq!(removed.rule_id(), "report-todo/todo/removed");
+        assert_eq!(
+            removed.message(),
+            "tracked TODO removed, issue 42 may be closeable"
+        );
+    }
+
+    #[test]
+    fn context_lines() {
+        let diff = "\
+diff --git a/a.rs b/a.rs
+index 1111111..2222222 100644
+--- a/a.rs
++++ b/a.rs
+@@ -1,4 +1,5 @@
+ fn main() {
+-    old();
++    // TODO: new
++    new();
+ 
+ }
+@@ -10,2 +11,3 @@ fn foo() {
+ a
++// TODO: b
+ c
+";
+
+        assert_eq!(
+            parse_hunks(diff),
+            vec![
+                (
+                    "a.rs",
+                    vec![2],
+                    vec![(2, "    // TODO: new"), (3, "    new();")]
+                ),
+                ("a.rs", vec![], vec![(12, "// TODO: b")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn plain_unified_diff() {
+        // As from `diff -u` or `hg diff`: no `diff --git` lines, timestamps after the paths.
+        let diff = "\
+--- a/src/one.py\t2024-01-01 00:00:00.000000000 +0000
++++ b/src/one.py\t2024-01-02 00:00:00.000000000 +0000
+@@ -1 +1,2 @@
+ import os
++# TODO: one
+--- two.py\tMon Jan  1 00:00:00 2024
++++ two.py\tTue Jan  2 00:00:00 2024
+@@ -3,0 +4 @@
++# TODO: two
+";
+
+        assert_eq!(
+            parse_hunks(diff),
+            vec![
+                ("src/one.py", vec![], vec![(2, "# TODO: one")]),
+                ("two.py", vec![], vec![(4, "# TODO: two")]),
+            ]
+        );
+        assert_eq!(
+            locations(diff),
+            vec![
+                ("./src/one.py".to_owned(), 2, 3),
+                ("./two.py".to_owned(), 4, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_newline_at_end_of_file() {
+        let diff = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -2 +2,2 @@
+-last
+\\ No newline at end of file
++last
++TODO
+\\ No newline at end of file
+@@ -10,0 +12 @@
++later
+";
+
+        assert_eq!(
+            parse_hunks(diff),
+            vec![
+                ("a.txt", vec![2], vec![(2, "last"), (3, "TODO")]),
+                ("a.txt", vec![], vec![(12, "later")]),
+            ]
+        );
+    }
+}
@@ -1,251 +1,236 @@
-//! Find TODOs in the diff since the fork point.
+//! Find TODOs in the diff since the fork point, or in the staged changes.
 
-use std::{iter::Peekable, ops::Range, path::PathBuf, str::Lines};
+use std::path::Path;
 
-use anyhow::{anyhow, Context};
+use anyhow::anyhow;
 use log::debug;
 
-use crate::todo_error::{Regexes, TodoError};
+use crate::{
+    paths::{repo_root, up_to},
+    todo_error::{Regexes, TodoError},
+};
 
-use super::Checker;
+use super::{diff::DiffChecker, Checker, ScanStats, SkipFiles};
 
-pub struct GitDiffChecker {}
+pub struct GitDiffChecker {
+    /// Commit or other ref to diff against. Defaults to the fork point from upstream.
+    pub base: Option<String>,
 
-impl Checker for GitDiffChecker {
-    fn process_spans(&self, config: &Regexes) -> anyhow::Result<Vec<TodoError>> {
-        debug!("Running `git remote -v`");
-        let remote = duct::cmd!("git", "remote", "-v")
-            .read()?
-            .lines()
-            .find_map(|line| {
-                if line.trim().starts_with("upstream") {
-                    Some("upstream")
-                } else {
-                    None
-                }
-            })
-            .unwrap_or("origin");
-
-        debug!("Running `git remote show {}`", remote);
-        let remote_ref = format!(
-            "{}/{}",
-            remote,
-            duct::cmd!("git", "remote", "show", remote)
-                .read()?
-                .lines()
-                .find_map(|line| {
-                    let line = line.trim();
-                    if line.starts_with("HEAD branch: ") {
-                        Some(&line["HEAD branch: ".len()..])
-                    } else {
-                        None
-                    }
-                })
-                .unwrap_or("master")
-        );
+    /// Diff the index against `HEAD` instead, ignoring `base`.
+    pub staged: bool,
 
-        debug!("Running `git merge-base --fork-point {}`", remote_ref);
-        let fork_point = duct::cmd!("git", "merge-base", "--fork-point", remote_ref).read()?;
+    /// Also report tracked findings on removed lines, see [`DiffChecker::report_removed`].
+    pub report_removed: bool,
 
-        debug!("Running `git diff --unified=0 {}`", fork_point);
-        let diff = duct::cmd!("git", "diff", "--unified=0", &fork_point)
-            .stderr_null()
-            .read()?;
-
-        let mut patch = UnifiedDiffParser::new(&diff)?;
-
-        let mut todo_errors = Vec::new();
-
-        loop {
-            if !patch.has_more() {
-                break;
-            }
-
-            let hunk = patch.read_hunk()?;
-            let path = PathBuf::from(&format!(
-                ".{}{}",
-                std::path::MAIN_SEPARATOR,
-                hunk.file
-                    .replace("/", &std::path::MAIN_SEPARATOR.to_string())
-            ));
+    pub skip: SkipFiles,
+}
 
-            for line in &hunk.added {
-                todo_errors.extend(TodoError::from_line(config, &path, line.line, line.row));
-            }
+impl Checker for GitDiffChecker {
+    fn process_spans(&self, config: &Regexes, stats: &ScanStats) -> anyhow::Result<Vec<TodoError>> {
+        // Git prints paths relative to the top of the working tree, wherever it's run.
+        let repo = repo_root(Path::new(".")).ok_or_else(|| anyhow!("not in a git repository"))?;
+        DiffChecker {
+            diff: self.read_diff(&repo)?,
+            root: up_to(&repo),
+            report_removed: self.report_removed,
+            skip: self.skip.clone(),
         }
-
-        Ok(todo_errors)
+        .process_spans(config, stats)
     }
 }
 
-#[derive(Debug)]
-pub struct UnifiedDiffParser<'a> {
-    source: &'a str,
-
-    lines: Peekable<Lines<'a>>,
-
-    current_file: &'a str,
-    current_patch_remove: Range<usize>,
-    current_patch_add: Range<usize>,
-}
+impl GitDiffChecker {
+    /// Runs `git diff` in `repo`. The diff is empty when nothing changed, e.g. with nothing staged.
+    fn read_diff(&self, repo: &Path) -> anyhow::Result<String> {
+        if self.staged {
+            debug!("Running `git diff --unified=0 --cached`");
+            return Ok(duct::cmd!("git", "diff", "--unified=0", "--cached")
+                .dir(repo)
+                .stderr_null()
+                .read()?);
+        }
 
-impl<'a> UnifiedDiffParser<'a> {
-    pub fn new(source: &'a str) -> anyhow::Result<Self> {
-        let mut parser = UnifiedDiffParser {
-            source,
-            lines: source.lines().peekable(),
-            current_file: "",
-            current_patch_remove: Range { start: 0, end: 0 },
-            current_patch_add: Range { start: 0, end: 0 },
+        let base = match &self.base {
+            Some(base) => base.clone(),
+            None => fork_point()?,
         };
 
-        parser.eat_file_header()?;
-
-        Ok(parser)
-    }
-
-    fn has_more(&mut self) -> bool {
-        self.lines.peek().is_some()
+        // `base` is always read as a revision, even if it starts with `-` or names a file.
+        debug!(
+            "Running `git diff --unified=0 --end-of-options {} --`",
+            base
+        );
+        Ok(duct::cmd!(
+            "git",
+            "diff",
+            "--unified=0",
+            "--end-of-options",
+            &base,
+            "--"
+        )
+        .dir(repo)
+        .stderr_null()
+        .read()?)
     }
+}
 
-    fn eat_file_header(&mut self) -> anyhow::Result<()> {
-        // Advance to the file indicator marker(s).
-        loop {
-            let line = self
-                .lines
-                .peek()
-                .context("before reading file header: next line exists")?;
-            if line.starts_with("--- ") || line.starts_with("+++ ") {
-                break;
+/// The commit the current branch forked from, on the upstream remote's default branch (or
+/// `origin`'s, without an `upstream` remote).
+fn fork_point() -> anyhow::Result<String> {
+    debug!("Running `git remote -v`");
+    let remote = duct::cmd!("git", "remote", "-v")
+        .read()
+        .map_err(|error| git_failure(error, "list the remotes"))?
+        .lines()
+        .find_map(|line| {
+            if line.trim().starts_with("upstream") {
+                Some("upstream")
             } else {
-                // ignore line
-                self.lines.next().unwrap();
+                None
             }
-        }
-
-        let source_file_line = self.lines.next().context("source line exists")?;
-        if !source_file_line.starts_with("--- ") {
-            return Err(anyhow!("remove line invalid: {}", source_file_line));
-        }
+        })
+        .unwrap_or("origin");
+
+    debug!("Running `git remote show {}`", remote);
+    let remote_ref = format!(
+        "{}/{}",
+        remote,
+        duct::cmd!("git", "remote", "show", remote)
+            .read()
+            .map_err(|error| {
+                git_failure(
+                    error,
+                    &format!("query remote `{}` for its default branch", remote),
+                )
+            })?
+            .lines()
+            .find_map(|line| {
+                let line = line.trim();
+                if line.starts_with("HEAD branch: ") {
+                    Some(&line["HEAD branch: ".len()..])
+                } else {
+                    None
+                }
+            })
+            .unwrap_or("master")
+    );
 
-        let target_file_line = self.lines.next().context("target line exists")?;
-        if !target_file_line.starts_with("+++ ") {
-            return Err(anyhow!("add line invalid: {}", target_file_line));
-        }
+    debug!("Running `git merge-base --fork-point {}`", remote_ref);
+    duct::cmd!("git", "merge-base", "--fork-point", &remote_ref)
+        .read()
+        .map_err(|error| git_failure(error, &format!("find the fork point from `{}`", remote_ref)))
+}
 
-        self.current_file = target_file_line.strip_prefix("+++ b/").unwrap();
-        debug!("Read added file: {}", self.current_file);
+/// Wraps a failed `git` invocation with what we were trying to do.
+///
+/// Shallow clones (common in CI) are missing the history needed to find a fork point, so point
+/// the user at fetching more of it, or at naming the base, instead of leaving them with an opaque
+/// `git` failure.
+fn git_failure(error: std::io::Error, action: &str) -> anyhow::Error {
+    explain_git_failure(error, action, is_shallow_repository())
+}
 
-        Ok(())
+fn explain_git_failure(error: std::io::Error, action: &str, is_shallow: bool) -> anyhow::Error {
+    let error = anyhow::Error::new(error).context(format!("failed to {}", action));
+    if is_shallow {
+        error.context(
+            "this is a shallow clone, so the history needed to compare against the upstream \
+            branch may be missing; try `git fetch --deepen=<depth>` or `git fetch --unshallow`, \
+            or pass a ref explicitly: `report_todo diff <REF>`",
+        )
+    } else {
+        error
     }
+}
 
-    fn read_hunk(&mut self) -> anyhow::Result<Hunk> {
-        // @@ -26,0 +27,6 @@ dependencies = [
-        let line = self
-            .lines
-            .next()
-            .context("before reading hunk: next line exists")?;
-
-        let mut parts = line
-            .strip_prefix("@@ ")
-            .context(anyhow!("patch line invalid: {}", line))?
-            .split(" ");
-
-        let current_patch_remove = {
-            let removed = parts
-                .next()
-                .unwrap()
-                .strip_prefix("-")
-                .context(anyhow!("patch missing removed section: {}", line))?;
-            let mut parts = removed.split(",");
-            let removed_row: usize = parts
-                .next()
-                .unwrap()
-                .parse()
-                .context("failed to parse row")?;
-            let removed_len: usize = parts
-                .next()
-                .context("missing removed length")
-                .and_then(|l| l.parse().context("failed to parse length"))
-                .unwrap_or(1);
-
-            Range {
-                start: removed_row,
-                end: removed_row + removed_len,
-            }
-        };
+fn is_shallow_repository() -> bool {
+    debug!("Running `git rev-parse --is-shallow-repository`");
+    duct::cmd!("git", "rev-parse", "--is-shallow-repository")
+        .stderr_null()
+        .read()
+        .map(|output| output.trim() == "true")
+        .unwrap_or(false)
+}
 
-        let current_patch_add = {
-            let added = parts
-                .next()
-                .unwrap()
-                .strip_prefix("+")
-                .context(anyhow!("patch missing added section: {}", line))?;
-            let mut parts = added.split(",");
-            let added_row: usize = parts
-                .next()
-                .unwrap()
-                .parse()
-                .context("failed to parse row")?;
-            let added_len: usize = parts
-                .next()
-                .context("missing added length")
-                .and_then(|l| l.parse().context("failed to parse length"))
-                .unwrap_or(1);
-
-            Range {
-                start: added_row,
-                end: added_row + added_len,
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkers::diff::UnifiedDiffParser;
+
+    #[test]
+    fn nothing_staged() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = temp_dir.path();
+        duct::cmd!("git", "init", "--quiet")
+            .dir(repo)
+            .run()
+            .unwrap();
+        std::fs::write(repo.join("a.rs"), "// TODO: not staged\n").unwrap();
+
+        let checker = GitDiffChecker {
+            base: None,
+            staged: true,
+            report_removed: false,
+            skip: SkipFiles::default(),
         };
+        let diff = checker.read_diff(repo).unwrap();
 
-        let mut hunk = Hunk {
-            file: self.current_file,
-            removed: Vec::new(),
-            added: Vec::new(),
-        };
+        // Which `DiffChecker` reads as no files, see `diff::tests::empty_diff`.
+        assert_eq!(diff, "");
+        assert!(UnifiedDiffParser::new(&diff).is_ok());
+    }
 
-        for row in current_patch_remove {
-            let line = self.lines.next().context("missing removed line")?[1..].trim_end();
-            hunk.removed.push(ChangedLine { line, row })
-        }
+    #[test]
+    fn base_is_not_an_option() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = temp_dir.path();
+        duct::cmd!("git", "init", "--quiet")
+            .dir(repo)
+            .run()
+            .unwrap();
+
+        let leaked = repo.join("leaked.diff");
+        let checker = GitDiffChecker {
+            base: Some(format!("--output={}", leaked.display())),
+            staged: false,
+            report_removed: false,
+            skip: SkipFiles::default(),
+        };
+        let diff = checker.read_diff(repo);
+        let wrote_output = leaked.exists();
 
-        for row in current_patch_add {
-            let line = self.lines.next().context("missing added line")?[1..].trim_end();
-            hunk.added.push(ChangedLine { line, row })
-        }
+        // An unknown revision, rather than `git diff --output=...`.
+        assert!(diff.is_err());
+        assert!(!wrote_output);
+    }
 
-        // Advance to the next file or next hunk.
-        loop {
-            if let Some(line) = self.lines.peek() {
-                if line.starts_with("diff ") {
-                    self.eat_file_header()?;
-                    break;
-                } else if line.starts_with("@@ ") {
-                    break;
-                } else {
-                    // ignore line
-                    self.lines.next().context("no more patch!")?;
-                }
-            } else {
-                // end of file
-                break;
-            }
-        }
+    #[test]
+    fn missing_fork_point_in_shallow_clone() {
+        let error = explain_git_failure(
+            std::io::Error::other("exited with status 1"),
+            "find the fork point from `origin/master`",
+            true,
+        );
+        let message = format!("{:#}", error);
 
-        Ok(hunk)
+        assert!(message.starts_with("this is a shallow clone"));
+        assert!(message.contains("git fetch --unshallow"));
+        assert!(message.contains("or pass a ref explicitly: `report_todo diff <REF>`"));
+        assert!(message.contains("failed to find the fork point from `origin/master`"));
+        assert!(message.contains("exited with status 1"));
     }
-}
 
-#[derive(Debug)]
-struct Hunk<'a> {
-    file: &'a str,
-    removed: Vec<ChangedLine<'a>>,
-    added: Vec<ChangedLine<'a>>,
-}
+    #[test]
+    fn missing_fork_point_in_full_clone() {
+        let error = explain_git_failure(
+            std::io::Error::other("exited with status 1"),
+            "find the fork point from `origin/master`",
+            false,
+        );
 
-#[derive(Debug)]
-struct ChangedLine<'a> {
-    line: &'a str,
-    row: usize,
+        assert_eq!(
+            format!("{:#}", error),
+            "failed to find the fork point from `origin/master`: exited with status 1"
+        );
+    }
 }
@@ -0,0 +1,119 @@
+//! Re-check just the given lines, e.g. previously reported findings after a fix.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use log::debug;
+use serde::Deserialize;
+
+use crate::{
+    language::Language,
+    todo_error::{Regexes, TodoError},
+};
+
+use super::{Checker, ScanStats};
+
+/// A line to re-check, as listed in `--only-locations`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub struct Location {
+    pub path: PathBuf,
+
+    /// 1-based, as reported.
+    pub line: usize,
+}
+
+/// Reads a JSON array of `{"path": ..., "line": ...}` objects.
+pub fn load_locations(path: &Path) -> anyhow::Result<Vec<Location>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&contents).with_context(|| {
+        format!(
+            "{} is not a JSON array of {{\"path\", \"line\"}} objects",
+            path.display()
+        )
+    })
+}
+
+/// Scans only the listed lines, reading only the files they're in. Files that no longer exist are
+/// skipped, as there's nothing left on their lines.
+pub struct LocationsChecker {
+    pub locations: Vec<Location>,
+}
+
+impl Checker for LocationsChecker {
+    fn process_spans(&self, config: &Regexes, stats: &ScanStats) -> anyhow::Result<Vec<TodoError>> {
+        let mut rows_by_file = BTreeMap::<&Path, BTreeSet<usize>>::new();
+        for location in &self.locations {
+            rows_by_file
+                .entry(&location.path)
+                .or_default()
+                .insert(location.line);
+        }
+
+        let mut todo_errors = Vec::new();
+        for (file_path, rows) in rows_by_file {
+            let contents = match std::fs::read_to_string(file_path) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    debug!("skipping {}: {}", file_path.display(), error);
+                    continue;
+                }
+            };
+            stats.record_file(file_path, contents.len());
+
+            let language = Language::detect(file_path, &config.languages);
+            for (row, line) in (1..).zip(contents.lines()) {
+                if rows.contains(&row) {
+                    todo_errors.extend(TodoError::from_line_in(
+                        config,
+                        file_path,
+                        language.as_ref(),
+                        line,
+                        row,
+                    ));
+                }
+            }
+        }
+
+        Ok(todo_errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regexes() -> Regexes {
+        Regexes::for_tests(r"TODO\(#(?P<issue_number>\d+)\):")
+    }
+
+    #[test]
+    fn scans_only_listed_lines() {
+        let rust = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_cases/rust.rs");
+        let location = |path: &Path, line| Location {
+            path: path.to_owned(),
+            line,
+        };
+        let stats = ScanStats::default();
+        let todo_errors = LocationsChecker {
+            locations: vec![
+                location(&rust, 17),
+                location(&rust, 3),
+                location(&rust, 11),
+                location(Path::new("missing.rs"), 1),
+            ],
+        }
+        .process_spans(&regexes(), &stats)
+        .unwrap();
+
+        let found = todo_errors
+            .iter()
+            .map(|todo_error| (todo_error.row(), todo_error.is_tracked()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![(11, false), (17, true)]);
+        assert_eq!(stats.files(), 1);
+    }
+}
@@ -1,9 +1,228 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
 use crate::todo_error::{Regexes, TodoError};
 
+pub mod diff;
 pub mod git_diff;
+pub mod locations;
 pub mod source_tree_simple;
 pub mod source_tree_syntect;
 
 pub trait Checker {
-    fn process_spans(&self, process_span: &Regexes) -> anyhow::Result<Vec<TodoError>>;
+    fn process_spans(
+        &self,
+        process_span: &Regexes,
+        stats: &ScanStats,
+    ) -> anyhow::Result<Vec<TodoError>>;
+}
+
+/// Running totals of the input a checker has scanned. Shared between walker threads.
+#[derive(Debug, Default)]
+pub struct ScanStats {
+    files: Mutex<Vec<PathBuf>>,
+    bytes: AtomicUsize,
+}
+
+impl ScanStats {
+    pub fn record_file(&self, path: &Path, bytes: usize) {
+        self.files.lock().unwrap().push(path.to_owned());
+        self.record_bytes(bytes);
+    }
+
+    pub fn record_bytes(&self, bytes: usize) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn files(&self) -> usize {
+        self.files.lock().unwrap().len()
+    }
+
+    /// Every file scanned, in no particular order.
+    pub fn file_paths(&self) -> Vec<PathBuf> {
+        self.files.lock().unwrap().clone()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+}
+
+/// Whether `entry` was modified at or after `since`. Entries whose modification time can't be read
+/// are assumed to be modified.
+fn is_modified_since(entry: &ignore::DirEntry, since: Option<SystemTime>) -> bool {
+    match since {
+        Some(since) => entry
+            .metadata()
+            .and_then(|metadata| Ok(metadata.modified()?))
+            .map_or(true, |modified| modified >= since),
+        None => true,
+    }
+}
+
+/// Files whose findings are noise: lock files and other generated code.
+#[derive(Debug, Clone, Default)]
+pub struct SkipFiles {
+    /// File names to skip wherever they are, e.g. `Cargo.lock`.
+    pub names: Vec<String>,
+
+    /// Skip files with an `@generated` marker near the top.
+    pub generated: bool,
+
+    /// Don't descend into nested git working trees, i.e. submodules.
+    pub submodules: bool,
+}
+
+/// How many lines from the top of a file to look for the `@generated` and
+/// [`IGNORE_FILE_MARKERS`] in.
+const GENERATED_MARKER_LINES: usize = 5;
+
+/// How many bytes from the top of a file [`SkipFiles::skips_file`] reads, so that a file with long
+/// lines, or none at all, isn't read whole.
+const GENERATED_MARKER_BYTES: u64 = 8 * 1024;
+
+/// Skip the whole file when near the top, in any case, e.g. `// report_todo:ignore-file`. Both
+/// spellings are accepted, as for the `report-todo:allow` line marker.
+const IGNORE_FILE_MARKERS: &[&str] = &["report_todo:ignore-file", "report-todo:ignore-file"];
+
+impl SkipFiles {
+    pub fn skips_name(&self, path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|file_name| self.names.iter().any(|name| file_name == name.as_str()))
+    }
+
+    /// Whether `contents` is generated or marked with one of [`IGNORE_FILE_MARKERS`], and should be
+    /// skipped. `contents` may be just the start of the file.
+    pub fn skips_contents(&self, contents: &str) -> bool {
+        contents.lines().take(GENERATED_MARKER_LINES).any(|line| {
+            if self.generated && line.contains("@generated") {
+                return true;
+            }
+            let line = line.to_lowercase();
+            IGNORE_FILE_MARKERS
+                .iter()
+                .any(|marker| line.contains(marker))
+        })
+    }
+
+    /// Whether the file at `path` should be skipped, reading just enough of it to tell.
+    pub fn skips_file(&self, path: &Path) -> bool {
+        if self.skips_name(path) {
+            return true;
+        }
+
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(_) => return false,
+        };
+        let mut head = Vec::new();
+        if file
+            .take(GENERATED_MARKER_BYTES)
+            .read_to_end(&mut head)
+            .is_err()
+        {
+            return false;
+        }
+        self.skips_contents(&String::from_utf8_lossy(&head))
+    }
+}
+
+/// Threads to walk with: `requested`, or by default all of `cpus` but two, leaving room for the
+/// rest of the system. Always at least one.
+fn walk_threads(requested: Option<usize>, cpus: usize) -> usize {
+    requested.unwrap_or_else(|| cpus.saturating_sub(2)).max(1)
+}
+
+/// Walks `roots` (at least one), honoring `.todoignore` as well as the usual ignore files.
+fn walk_builder(
+    roots: &[PathBuf],
+    max_depth: Option<usize>,
+    skip: &SkipFiles,
+) -> ignore::WalkBuilder {
+    let mut builder = ignore::WalkBuilder::new(&roots[0]);
+    for root in &roots[1..] {
+        builder.add(root);
+    }
+    builder.max_depth(max_depth);
+    builder.add_custom_ignore_filename(".todoignore");
+    if skip.submodules {
+        builder.filter_entry(|entry| !is_nested_work_tree(entry));
+    }
+    builder
+}
+
+/// Whether `entry` is the top of a git working tree below one of the roots, such as a submodule
+/// (whose `.git` is a file) or a nested clone.
+fn is_nested_work_tree(entry: &ignore::DirEntry) -> bool {
+    entry.depth() > 0
+        && entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_dir())
+        && entry.path().join(".git").exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_generated_files() {
+        let skip = SkipFiles {
+            names: vec!["Cargo.lock".to_owned()],
+            generated: true,
+            submodules: true,
+        };
+
+        assert!(skip.skips_name(Path::new("a/Cargo.lock")));
+        assert!(!skip.skips_name(Path::new("a/Cargo.toml")));
+        assert!(skip.skips_contents("// Code generated by protoc. @generated\n// TODO\n"));
+        assert!(!skip.skips_contents("1\n2\n3\n4\n5\n// @generated\n"));
+        assert!(!SkipFiles::default().skips_contents("// @generated\n"));
+    }
+
+    #[test]
+    fn walk_threads_is_at_least_one() {
+        assert_eq!(walk_threads(None, 1), 1);
+        assert_eq!(walk_threads(None, 2), 1);
+        assert_eq!(walk_threads(None, 8), 6);
+        assert_eq!(walk_threads(Some(3), 1), 3);
+        assert_eq!(walk_threads(Some(0), 8), 1);
+    }
+
+    #[test]
+    fn skips_ignored_files() {
+        let skip = SkipFiles::default();
+
+        assert!(skip.skips_contents("#!/bin/sh\n# report_todo:ignore-file\n# TODO\n"));
+        assert!(skip.skips_contents("<!-- Report_Todo:Ignore-File -->\n"));
+        assert!(skip.skips_contents("/* report-todo:ignore-file: examples */\n"));
+        assert!(!skip.skips_contents("1\n2\n3\n4\n5\n// report_todo:ignore-file\n"));
+        assert!(!skip.skips_contents("// report_todo:allow\n"));
+    }
+
+    #[test]
+    fn reads_a_prefix_of_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let marked = dir.join("marked.rs");
+        std::fs::write(&marked, "// @generated\n".to_owned() + &"x".repeat(1 << 20)).unwrap();
+        let long_line = dir.join("long_line.rs");
+        std::fs::write(&long_line, "x".repeat(1 << 20) + "\n// @generated\n").unwrap();
+
+        let skip = SkipFiles {
+            generated: true,
+            ..SkipFiles::default()
+        };
+        let skipped = (skip.skips_file(&marked), skip.skips_file(&long_line));
+
+        // The marker past the first 8 KiB isn't seen.
+        assert_eq!(skipped, (true, false));
+    }
 }
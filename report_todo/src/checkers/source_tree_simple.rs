@@ -1,30 +1,53 @@
 //! Inspect all files in a source tree and look for TODOs in each line.
 
 use std::{
-    path::PathBuf,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
+    time::SystemTime,
 };
 
 use ignore::WalkState;
 use log::debug;
 
-use crate::todo_error::{Regexes, TodoError};
+use crate::{
+    language::Language,
+    notebook::{code_cells, is_notebook},
+    todo_error::{Regexes, TodoError},
+};
 
-use super::Checker;
+use super::{is_modified_since, walk_builder, walk_threads, Checker, ScanStats, SkipFiles};
 
 pub struct SourceTreeSimpleChecker {
-    pub root_dir: PathBuf,
+    /// Directories or files to scan.
+    pub roots: Vec<PathBuf>,
+
+    /// Only scan files modified at or after this time.
+    pub modified_since: Option<SystemTime>,
+
+    /// Don't descend more than this many directories below the roots. 1 scans only the roots'
+    /// direct entries.
+    pub max_depth: Option<usize>,
+
+    /// Read files larger than this many bytes a line at a time, rather than all at once, to bound
+    /// memory use on huge (e.g. generated) files.
+    pub stream_above: Option<u64>,
+
+    /// See [`walk_threads`].
+    pub threads: Option<usize>,
+
+    pub skip: SkipFiles,
 }
 
 impl Checker for SourceTreeSimpleChecker {
-    fn process_spans(&self, config: &Regexes) -> anyhow::Result<Vec<TodoError>> {
+    fn process_spans(&self, config: &Regexes, stats: &ScanStats) -> anyhow::Result<Vec<TodoError>> {
         let todo_errors = Arc::new(Mutex::new(Vec::new()));
 
-        let num_threads = num_cpus::get() - 2;
+        let num_threads = walk_threads(self.threads, num_cpus::get());
         debug!("Using {} threads", num_threads);
 
-        ignore::WalkBuilder::new(&self.root_dir)
-            .add_custom_ignore_filename(".todoignore")
+        walk_builder(&self.roots, self.max_depth, &self.skip)
             .threads(num_threads)
             .build_parallel()
             .run(|| {
@@ -33,17 +56,29 @@ impl Checker for SourceTreeSimpleChecker {
                 Box::new(move |entry| {
                     let entry = entry.expect("walking directory entry should not have i/o errors");
                     let file_path = entry.path();
-                    if file_path.is_file() {
-                        if let Ok(file_contents) = std::fs::read_to_string(file_path) {
-                            for (row_zero_indexed, line) in file_contents.lines().enumerate() {
-                                todo_errors.lock().unwrap().extend(TodoError::from_line(
-                                    config,
-                                    file_path,
-                                    line,
-                                    row_zero_indexed + 1,
-                                ));
-                            }
-                        }
+                    if !file_path.is_file() || !is_modified_since(&entry, self.modified_since) {
+                        return WalkState::Continue;
+                    }
+                    // Before reading the whole file, so skipped files aren't counted as scanned.
+                    if self.skip.skips_file(file_path) {
+                        debug!("skipping generated or ignored file {}", file_path.display());
+                        return WalkState::Continue;
+                    }
+
+                    let language = Language::detect(file_path, &config.languages);
+                    let language = language.as_ref();
+                    if self.should_stream(&entry) {
+                        debug!("streaming {}", file_path.display());
+                        let found = stream_file(config, file_path, language, stats);
+                        todo_errors.lock().unwrap().extend(found);
+                    } else if let Ok(file_contents) = std::fs::read_to_string(file_path) {
+                        stats.record_file(file_path, file_contents.len());
+                        let found = if is_notebook(file_path) {
+                            scan_notebook(config, file_path, language, &file_contents)
+                        } else {
+                            scan_lines(config, file_path, language, &file_contents)
+                        };
+                        todo_errors.lock().unwrap().extend(found);
                     }
 
                     WalkState::Continue
@@ -53,3 +88,157 @@ impl Checker for SourceTreeSimpleChecker {
         Ok(Arc::try_unwrap(todo_errors).unwrap().into_inner().unwrap())
     }
 }
+
+impl SourceTreeSimpleChecker {
+    fn should_stream(&self, entry: &ignore::DirEntry) -> bool {
+        // A notebook has to be parsed whole.
+        if is_notebook(entry.path()) {
+            return false;
+        }
+        match self.stream_above {
+            Some(threshold) => entry
+                .metadata()
+                .is_ok_and(|metadata| metadata.len() > threshold),
+            None => false,
+        }
+    }
+}
+
+fn scan_lines(
+    config: &Regexes,
+    file_path: &Path,
+    language: Option<&Language>,
+    contents: &str,
+) -> Vec<TodoError> {
+    let previous_lines = std::iter::once(None).chain(contents.lines().map(Some));
+    previous_lines
+        .zip(contents.lines())
+        .enumerate()
+        .flat_map(|(row_zero_indexed, (previous_line, line))| {
+            TodoError::from_line_after(
+                config,
+                file_path,
+                language,
+                previous_line,
+                line,
+                row_zero_indexed + 1,
+            )
+        })
+        .collect()
+}
+
+/// Scans only the code cells of a notebook, numbering lines within each cell. A notebook that
+/// can't be parsed is scanned as plain text instead.
+fn scan_notebook(
+    config: &Regexes,
+    file_path: &Path,
+    language: Option<&Language>,
+    contents: &str,
+) -> Vec<TodoError> {
+    let cells = match code_cells(contents) {
+        Ok(cells) => cells,
+        Err(error) => {
+            debug!("scanning {} as text: {}", file_path.display(), error);
+            return scan_lines(config, file_path, language, contents);
+        }
+    };
+
+    let mut todo_errors = Vec::new();
+    for (cell, source) in cells {
+        for mut todo_error in scan_lines(config, file_path, language, &source) {
+            todo_error.set_cell(cell);
+            todo_errors.push(todo_error);
+        }
+    }
+    todo_errors
+}
+
+/// Scans `file_path` a line at a time. Like the whole-file path, a file that isn't valid UTF-8
+/// yields nothing, even if the invalid line comes after findings, and isn't counted as scanned.
+fn stream_file(
+    config: &Regexes,
+    file_path: &Path,
+    language: Option<&Language>,
+    stats: &ScanStats,
+) -> Vec<TodoError> {
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut todo_errors = Vec::new();
+    let mut bytes = 0;
+    let mut previous_line = None;
+    for (row_zero_indexed, line) in BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                debug!("not scanning {}: {}", file_path.display(), error);
+                return Vec::new();
+            }
+        };
+        bytes += line.len() + 1;
+        todo_errors.extend(TodoError::from_line_after(
+            config,
+            file_path,
+            language,
+            previous_line.as_deref(),
+            &line,
+            row_zero_indexed + 1,
+        ));
+        previous_line = Some(line);
+    }
+    stats.record_file(file_path, bytes);
+    todo_errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regexes() -> Regexes {
+        Regexes::for_tests(r"TODO\(#(?P<issue_number>\d+)\):")
+    }
+
+    #[test]
+    fn streaming_matches_whole_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let valid = dir.join("valid.rs");
+        let invalid = dir.join("invalid.rs");
+        let generated = dir.join("generated.rs");
+        std::fs::write(&valid, "// TODO: a\nlet a = 1;\n// TODO(#1): b\n").unwrap();
+        std::fs::write(&invalid, b"// TODO: a\n\xff\n// TODO: b\n").unwrap();
+        std::fs::write(&generated, "// @generated\n// TODO: a\n").unwrap();
+
+        let scan = |path: &Path, stream_above| {
+            let stats = ScanStats::default();
+            let todo_errors = SourceTreeSimpleChecker {
+                roots: vec![path.to_owned()],
+                modified_since: None,
+                max_depth: None,
+                stream_above,
+                threads: Some(1),
+                skip: SkipFiles {
+                    generated: true,
+                    ..SkipFiles::default()
+                },
+            }
+            .process_spans(&regexes(), &stats)
+            .unwrap()
+            .iter()
+            .map(|todo_error| (todo_error.row(), todo_error.is_tracked()))
+            .collect::<Vec<_>>();
+            (todo_errors, stats.files(), stats.bytes())
+        };
+
+        assert_eq!(scan(&valid, Some(0)), scan(&valid, None));
+        assert_eq!(scan(&valid, None), (vec![(1, false), (3, true)], 1, 37));
+        // Findings before the invalid line are dropped too.
+        assert_eq!(scan(&invalid, Some(0)), scan(&invalid, None));
+        assert_eq!(scan(&invalid, None), (vec![], 0, 0));
+        // Skipped files aren't counted as scanned either.
+        assert_eq!(scan(&generated, Some(0)), (vec![], 0, 0));
+        assert_eq!(scan(&generated, None), (vec![], 0, 0));
+    }
+}
@@ -3,15 +3,19 @@
 use std::{
     path::PathBuf,
     sync::{Arc, Mutex},
+    time::SystemTime,
 };
 
 use ignore::WalkState;
 use log::debug;
 use span::Span;
 
-use crate::todo_error::{Regexes, TodoError};
+use crate::{
+    language::Language,
+    todo_error::{Regexes, TodoError},
+};
 
-use super::Checker;
+use super::{is_modified_since, walk_builder, walk_threads, Checker, ScanStats, SkipFiles};
 
 // TODO(#4): capture usages of `todo!()` macro in rust?
 struct ScopeTracker<'a> {
@@ -97,7 +101,7 @@ impl<'a> Iterator for ScopeTracker<'a> {
 ///
 /// TODO(#5): this + the syntect parser could be combined into a single iterator. the syntect ScopeStackOps would have to be staged and only processed when another comment is requested.
 /// TODO(#4): refactor to allow multiple scopes to scan for?
-struct CommentScopeStack<'a> {
+pub struct CommentScopeStack<'a> {
     /// original text that's being parsed
     original: Span<'a>,
 
@@ -111,7 +115,7 @@ struct CommentScopeStack<'a> {
 }
 
 impl<'a> CommentScopeStack<'a> {
-    fn new(text: Span<'a>) -> Self {
+    pub fn new(text: Span<'a>) -> Self {
         Self {
             original: text,
             current_comment_start: None,
@@ -127,7 +131,7 @@ impl<'a> CommentScopeStack<'a> {
     /// TODO(#5): should "get comments" be a callback?
     ///
     /// Note: we need to pass the original line because syntect doesn't preserve mappings to the original source.
-    fn process_ops_for_line(
+    pub fn process_ops_for_line(
         &mut self,
         ops: impl Iterator<Item = (usize, syntect::parsing::ScopeStackOp)>,
         original_line: Span<'a>,
@@ -198,11 +202,24 @@ impl<'a> CommentScopeStack<'a> {
 }
 
 pub struct SourceTreeSyntectChecker {
-    pub root_dir: PathBuf,
+    /// Directories or files to scan.
+    pub roots: Vec<PathBuf>,
+
+    /// Only scan files modified at or after this time.
+    pub modified_since: Option<SystemTime>,
+
+    /// Don't descend more than this many directories below the roots. 1 scans only the roots'
+    /// direct entries.
+    pub max_depth: Option<usize>,
+
+    /// See [`walk_threads`].
+    pub threads: Option<usize>,
+
+    pub skip: SkipFiles,
 }
 
 impl Checker for SourceTreeSyntectChecker {
-    fn process_spans(&self, config: &Regexes) -> anyhow::Result<Vec<TodoError>> {
+    fn process_spans(&self, config: &Regexes, stats: &ScanStats) -> anyhow::Result<Vec<TodoError>> {
         let todo_errors = Arc::new(Mutex::new(Vec::new()));
 
         let syntax_set = {
@@ -228,11 +245,10 @@ impl Checker for SourceTreeSyntectChecker {
             builder.build()
         };
 
-        let num_threads = num_cpus::get() - 2;
+        let num_threads = walk_threads(self.threads, num_cpus::get());
         debug!("Using {} threads", num_threads);
 
-        ignore::WalkBuilder::new(&self.root_dir)
-            .add_custom_ignore_filename(".todoignore")
+        walk_builder(&self.roots, self.max_depth, &self.skip)
             .threads(num_threads)
             .build_parallel()
             .run(|| {
@@ -242,14 +258,19 @@ impl Checker for SourceTreeSyntectChecker {
                 Box::new(move |entry| {
                     let entry = entry.expect("walking directory entry should not have i/o errors");
                     let file_path = entry.path();
-                    if file_path.is_file() {
+                    if file_path.is_file()
+                        && is_modified_since(&entry, self.modified_since)
+                        && !self.skip.skips_file(file_path)
+                    {
                         if let Ok(Some(syntax_ref)) = syntax_set.find_syntax_for_file(entry.path())
                         {
                             debug!("working on {}", file_path.display());
 
                             let mut state = syntect::parsing::ParseState::new(syntax_ref);
+                            let language = Language::detect(file_path, &config.languages);
 
                             let file_contents = std::fs::read_to_string(file_path).unwrap();
+                            stats.record_file(file_path, file_contents.len());
                             let file_span =
                                 Span::new(&file_contents, 0, file_contents.len()).unwrap();
                             let mut stack = CommentScopeStack::new(file_span.clone());
@@ -263,8 +284,14 @@ impl Checker for SourceTreeSyntectChecker {
                                             line,
                                         )
                                         .into_iter()
+                                        .filter(|span| !is_in_shebang(span, &file_contents))
                                         .flat_map(|span| {
-                                            TodoError::from_comment(config, file_path, span)
+                                            TodoError::from_comment(
+                                                config,
+                                                file_path,
+                                                language.as_ref(),
+                                                span,
+                                            )
                                         }),
                                 );
                             }
@@ -280,3 +307,140 @@ impl Checker for SourceTreeSyntectChecker {
         Ok(Arc::try_unwrap(todo_errors).unwrap().into_inner().unwrap())
     }
 }
+
+/// Whether `comment` is (part of) a script's `#!` line, which syntaxes for shell, Python etc.
+/// scope as a comment.
+fn is_in_shebang(comment: &Span, file_contents: &str) -> bool {
+    file_contents.starts_with("#!")
+        && comment.start() < file_contents.find('\n').unwrap_or(file_contents.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regexes() -> Regexes {
+        Regexes::for_tests(r"todo\(#(?P<issue_number>\d+)\):")
+    }
+
+    /// The findings in `test_cases/<fixture>`, by row.
+    fn findings(fixture: &str) -> Vec<TodoError> {
+        let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test_cases")
+            .join(fixture);
+        let mut findings = SourceTreeSyntectChecker {
+            roots: vec![fixture],
+            modified_since: None,
+            max_depth: None,
+            threads: Some(1),
+            skip: SkipFiles::default(),
+        }
+        .process_spans(&regexes(), &ScanStats::default())
+        .unwrap();
+        findings.sort_by_key(|todo_error| (todo_error.row(), todo_error.col()));
+        findings
+    }
+
+    /// The row of each finding in `test_cases/<fixture>`, and whether it's tracked.
+    fn scan(fixture: &str) -> Vec<(usize, bool)> {
+        findings(fixture)
+            .iter()
+            .map(|todo_error| (todo_error.row(), todo_error.is_tracked()))
+            .collect()
+    }
+
+    #[test]
+    fn only_comments_in_fixture() {
+        let rows = scan("rust.rs");
+
+        assert_eq!(
+            rows,
+            [
+                (11, false),
+                (12, false),
+                (13, false),
+                (14, false),
+                (15, false),
+                (16, true),
+                (17, true),
+                (18, false),
+                (19, false),
+                (20, false),
+                (21, false),
+                // In a nested block comment.
+                (32, false),
+                (36, false),
+                (38, false),
+                (39, false),
+                (40, true),
+                (41, true),
+                (42, false),
+                (57, false),
+            ]
+        );
+        // Not in string literals, raw or otherwise, even after a nested block comment.
+        assert!(!rows
+            .iter()
+            .any(|(row, _)| (27..=30).contains(row) || *row == 33));
+    }
+
+    #[test]
+    fn only_comments_in_other_fixtures() {
+        // Not the verbatim or interpolated strings, nor `todo` in a Python or recipe string.
+        assert_eq!(
+            scan("csharp.cs"),
+            [(6, false), (15, false), (16, true), (18, false), (19, true)]
+        );
+        assert_eq!(scan("python.py"), [(2, true), (8, false)]);
+        assert_eq!(scan("Makefile"), [(1, true), (3, false)]);
+    }
+
+    #[test]
+    fn not_shebangs_or_yaml_values() {
+        // `todo` in a key's value and in the plain scalar before a comment aren't comments.
+        assert_eq!(scan("yaml.yaml"), [(1, false), (3, true)]);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let script = dir.join("run.sh");
+        std::fs::write(
+            &script,
+            "#!/usr/bin/env todo-runner\n# TODO(#6): shell comment\necho todo\n",
+        )
+        .unwrap();
+        let rows = scan(script.to_str().unwrap());
+
+        assert_eq!(rows, [(2, true)]);
+    }
+
+    #[test]
+    fn style_of_whole_lines() {
+        let styles = findings("style.c")
+            .into_iter()
+            .map(|mut todo_error| {
+                todo_error.check_style();
+                let style = serde_json::to_value(&todo_error).unwrap()["style"].clone();
+                (todo_error.row(), todo_error.col(), style)
+            })
+            .collect::<Vec<_>>();
+
+        // Columns and tab indentation are of the whole line, not just the comment, and the line
+        // break isn't trailing whitespace.
+        assert_eq!(
+            styles,
+            [
+                (2, 19, serde_json::Value::Null),
+                (
+                    3,
+                    15,
+                    serde_json::json!(["TODO comment is indented with tabs"])
+                ),
+                (
+                    4,
+                    8,
+                    serde_json::json!(["TODO comment has trailing whitespace"])
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Checkstyle XML, for CI plugins that collect linter warnings, e.g. Jenkins' Warnings NG.
+
+use std::{collections::BTreeMap, io::Write, path::Path};
+
+use anyhow::{anyhow, Context};
+
+use crate::{
+    emitter::Emitter,
+    json_emitter::Metadata,
+    paths::display_path,
+    todo_error::{Severity, TodoError},
+};
+
+/// `--format checkstyle`.
+pub struct CheckstyleEmitter;
+
+impl Emitter for CheckstyleEmitter {
+    fn write_to(
+        &self,
+        out: &mut dyn Write,
+        findings: &[TodoError],
+        _metadata: &Metadata,
+    ) -> anyhow::Result<()> {
+        write_to(out, findings)
+    }
+
+    fn validate(&self, output: &str, findings: &[TodoError]) -> anyhow::Result<()> {
+        validate(output, findings.len()).context("invalid Checkstyle document")
+    }
+}
+
+/// Writes all findings to `writer` as one Checkstyle document. Findings are grouped into a
+/// `<file>` per path, in path order, and sorted by position within each.
+pub fn write_to(mut writer: impl Write, findings: &[TodoError]) -> anyhow::Result<()> {
+    let mut by_file = BTreeMap::<&Path, Vec<&TodoError>>::new();
+    for todo_error in findings {
+        by_file
+            .entry(todo_error.file_path())
+            .or_default()
+            .push(todo_error);
+    }
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<checkstyle version="4.3">"#)?;
+    for (path, mut findings) in by_file {
+        findings.sort_by_key(|todo_error| (todo_error.row(), todo_error.col()));
+        writeln!(
+            writer,
+            r#"  <file name="{}">"#,
+            escape_attribute(&display_path(path))
+        )?;
+        for todo_error in findings {
+            let severity = match todo_error.severity() {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Info => "info",
+            };
+            writeln!(
+                writer,
+                r#"    <error line="{}" column="{}" severity="{}" message="{}" source="{}"/>"#,
+                todo_error.row(),
+                todo_error.col(),
+                severity,
+                escape_attribute(todo_error.message()),
+                escape_attribute(todo_error.rule_id())
+            )?;
+        }
+        writeln!(writer, "  </file>")?;
+    }
+    writeln!(writer, "</checkstyle>")?;
+    Ok(())
+}
+
+fn escape_attribute(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            // Not allowed in XML 1.0 at all.
+            c if c.is_control() => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Checks that `document` is a Checkstyle document with `findings` errors.
+pub fn validate(document: &str, findings: usize) -> anyhow::Result<()> {
+    if !document.starts_with("<?xml") || !document.trim_end().ends_with("</checkstyle>") {
+        return Err(anyhow!("expected a <checkstyle> document"));
+    }
+    if document.matches("<error ").count() != findings {
+        return Err(anyhow!("expected {} errors", findings));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_findings_by_file() {
+        let mut other = TodoError::sample(true);
+        other.set_file_path("./src/a.rs".into());
+        let findings = [TodoError::sample(false), other, TodoError::sample(true)];
+        let mut document = Vec::new();
+        write_to(&mut document, &findings).unwrap();
+
+        assert_eq!(
+            String::from_utf8(document).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<checkstyle version="4.3">
+  <file name="./src/a.rs">
+    <error line="1" column="8" severity="info" message="tracked sample" source="report-todo/todo/tracked"/>
+  </file>
+  <file name="./src/sample.rs">
+    <error line="1" column="8" severity="info" message="tracked sample" source="report-todo/todo/tracked"/>
+    <error line="2" column="8" severity="error" message="TODO found without issue number" source="report-todo/todo/untracked"/>
+  </file>
+</checkstyle>
+"#
+        );
+    }
+
+    #[test]
+    fn attributes_are_escaped() {
+        assert_eq!(
+            escape_attribute(r#"a < "b" & 'c'"#),
+            "a &lt; &quot;b&quot; &amp; &apos;c&apos;"
+        );
+        assert_eq!(escape_attribute("a\nb\u{1}"), "a&#10;b\u{fffd}");
+    }
+}
@@ -1,5 +1,8 @@
 use std::io::Write;
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+use termcolor::{Buffer, Color, ColorChoice, ColorSpec, NoColor, StandardStream, WriteColor};
+
+use crate::{emitter::Emitter, json_emitter::Metadata, todo_error::TodoError};
 
 pub enum Style {
     LineNumber,
@@ -10,15 +13,118 @@ pub enum Style {
     Normal,
 }
 
-pub struct ColoredWriter {
-    inner: StandardStream,
+pub struct ColoredWriter<W = StandardStream> {
+    inner: W,
+
+    /// Print locations as absolute `file://` URLs instead of bare paths.
+    pub file_urls: bool,
+
+    /// Longer source lines are cut down to this many characters around the finding.
+    pub max_width: usize,
+
+    /// Print each help message of untracked findings once, after all findings, instead of in every
+    /// finding.
+    pub help_once: bool,
+
+    /// Help messages held back by `help_once`, in the order first seen.
+    folded_help: Vec<String>,
 }
 
+/// Snippet width when neither `--max-width` nor `$COLUMNS` is given.
+pub const DEFAULT_MAX_WIDTH: usize = 120;
+
 impl ColoredWriter {
+    /// Writes to stderr, in color if it's supported.
     pub fn new() -> Self {
+        Self::stderr(ColorChoice::Auto)
+    }
+
+    pub fn stderr(color_choice: ColorChoice) -> Self {
+        Self::with_sink(StandardStream::stderr(color_choice))
+    }
+
+    pub fn stdout(color_choice: ColorChoice) -> Self {
+        Self::with_sink(StandardStream::stdout(color_choice))
+    }
+}
+
+impl Default for ColoredWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `--format human`, the snippets printed on stderr, here without colors.
+pub struct HumanEmitter;
+
+impl Emitter for HumanEmitter {
+    fn write_to(
+        &self,
+        out: &mut dyn Write,
+        findings: &[TodoError],
+        _metadata: &Metadata,
+    ) -> anyhow::Result<()> {
+        let mut writer = ColoredWriter::with_sink(NoColor::new(out));
+        for todo_error in findings {
+            writer.write_error(todo_error)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Does nothing: the snippets are printed on stderr as findings are reported, subject to
+    /// options like `--max-per-file` that only apply there.
+    fn finalize(&self, _findings: &[TodoError], _metadata: &Metadata) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl ColoredWriter<Buffer> {
+    /// Writes to memory, without colors. Read back with [`ColoredWriter::into_inner`].
+    pub fn buffer() -> Self {
+        Self::with_sink(Buffer::no_color())
+    }
+}
+
+impl<W: WriteColor> ColoredWriter<W> {
+    /// Writes to `inner`, e.g. `StandardStream::stdout`.
+    pub fn with_sink(inner: W) -> Self {
         Self {
-            inner: StandardStream::stderr(ColorChoice::Auto),
+            inner,
+            file_urls: false,
+            max_width: DEFAULT_MAX_WIDTH,
+            help_once: false,
+            folded_help: Vec::new(),
+        }
+    }
+
+    /// With `help_once`, holds `help_message` back to print with [`Self::write_folded_help`] and
+    /// returns true.
+    pub fn fold_help(&mut self, help_message: &str) -> bool {
+        if !self.help_once {
+            return false;
+        }
+        if !self.folded_help.iter().any(|folded| folded == help_message) {
+            self.folded_help.push(help_message.to_owned());
+        }
+        true
+    }
+
+    /// Prints the help messages held back by [`Self::fold_help`].
+    pub fn write_folded_help(&mut self) -> std::io::Result<()> {
+        for help_message in std::mem::take(&mut self.folded_help) {
+            self.write("= ", Style::LineNumber)?;
+            self.write(format!("{}\n", help_message), Style::Normal)?;
         }
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
     }
 
     pub fn write(&mut self, message: impl std::fmt::Display, style: Style) -> std::io::Result<()> {
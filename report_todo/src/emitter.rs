@@ -0,0 +1,57 @@
+//! The interface every report format implements, so that stdout, `--also` files, `--output-dir`
+//! and `--selftest` all write a format the same way.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+
+use crate::{json_emitter::Metadata, todo_error::TodoError};
+
+/// A report format. Findings are collected until the scan is done and then emitted at once, as
+/// formats like SARIF and Checkstyle need all of them before writing anything.
+pub trait Emitter {
+    /// Writes the report of `findings` to `out`.
+    fn write_to(
+        &self,
+        out: &mut dyn Write,
+        findings: &[TodoError],
+        metadata: &Metadata,
+    ) -> anyhow::Result<()>;
+
+    /// Checks that `output`, as written by [`Emitter::write_to`] for `findings`, has the layout
+    /// consumers expect. By default, that it mentions each finding's message.
+    fn validate(&self, output: &str, findings: &[TodoError]) -> anyhow::Result<()> {
+        for todo_error in findings {
+            if !output.contains(todo_error.message()) {
+                return Err(anyhow!("output is missing `{}`", todo_error.message()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits the report of the whole scan, once it's done. By default, to stdout.
+    fn finalize(&self, findings: &[TodoError], metadata: &Metadata) -> anyhow::Result<()> {
+        let stdout = std::io::stdout();
+        let mut out = std::io::BufWriter::new(stdout.lock());
+        self.write_to(&mut out, findings, metadata)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Writes one report per source file into `output_dir`, also for the `scanned` files without
+    /// findings if `empty_reports` is set. Only JSON reports can be split up like this.
+    fn write_per_file(
+        &self,
+        _findings: &[TodoError],
+        _scanned: &[PathBuf],
+        _output_dir: &Path,
+        _empty_reports: bool,
+    ) -> anyhow::Result<()> {
+        Err(anyhow!(
+            "--output-dir requires `--format json` or `--format json-pretty`"
+        ))
+    }
+}
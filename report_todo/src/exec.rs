@@ -0,0 +1,124 @@
+//! Running a user-supplied command for each finding.
+
+use std::{
+    process::ExitStatus,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::anyhow;
+use log::debug;
+
+use crate::{paths::display_path, todo_error::TodoError};
+
+/// A command line with `{file}`, `{line}`, `{col}`, `{message}`, `{keyword}` and `{rule}`
+/// placeholders, filled in per finding.
+///
+/// The template is split into arguments on whitespace before the placeholders are filled in, and
+/// is run directly rather than by a shell, so messages can't inject further commands.
+#[derive(Debug)]
+pub struct CommandTemplate {
+    args: Vec<String>,
+}
+
+impl CommandTemplate {
+    pub fn new(template: &str) -> anyhow::Result<Self> {
+        let args = template
+            .split_whitespace()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        if args.is_empty() {
+            return Err(anyhow!("--exec command is empty"));
+        }
+        Ok(CommandTemplate { args })
+    }
+
+    /// The program and its arguments for `todo_error`.
+    pub fn fill(&self, todo_error: &TodoError) -> Vec<String> {
+        self.args
+            .iter()
+            .map(|arg| {
+                arg.replace("{file}", &display_path(todo_error.file_path()))
+                    .replace("{line}", &todo_error.row().to_string())
+                    .replace("{col}", &todo_error.col().to_string())
+                    .replace("{keyword}", todo_error.keyword())
+                    .replace("{rule}", todo_error.rule_id())
+                    .replace("{message}", todo_error.message())
+            })
+            .collect()
+    }
+}
+
+/// Runs `template` for each of `findings`, at most `jobs` at a time, or just prints the commands
+/// if `dry_run`. Fails if any command couldn't be run or exited unsuccessfully.
+pub fn run_for_each(
+    template: &CommandTemplate,
+    findings: &[&TodoError],
+    jobs: usize,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let commands = findings
+        .iter()
+        .map(|todo_error| template.fill(todo_error))
+        .collect::<Vec<_>>();
+
+    if dry_run {
+        for command in &commands {
+            println!("{}", command.join(" "));
+        }
+        return Ok(());
+    }
+
+    let next = AtomicUsize::new(0);
+    let failures = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.max(1).min(commands.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let command = match commands.get(i) {
+                    Some(command) => command,
+                    None => break,
+                };
+
+                debug!("Running `{}`", command.join(" "));
+                let result = duct::cmd(&command[0], &command[1..])
+                    .unchecked()
+                    .run()
+                    .map(|output| output.status);
+                if let Some(failure) = describe_failure(result) {
+                    failures.lock().unwrap().push((i, failure));
+                }
+            });
+        }
+    });
+
+    let mut failures = failures.into_inner().unwrap();
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    failures.sort();
+    for (i, failure) in &failures {
+        eprintln!(
+            "--exec failed for {}:{}: {}",
+            display_path(findings[*i].file_path()),
+            findings[*i].row(),
+            failure
+        );
+    }
+    Err(anyhow!(
+        "--exec failed for {} of {} findings",
+        failures.len(),
+        findings.len()
+    ))
+}
+
+fn describe_failure(result: std::io::Result<ExitStatus>) -> Option<String> {
+    match result {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(status.to_string()),
+        Err(error) => Some(error.to_string()),
+    }
+}
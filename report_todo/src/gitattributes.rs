@@ -0,0 +1,137 @@
+//! Language overrides from `.gitattributes`, e.g. `*.inc linguist-language=PHP`.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Attributes naming a file's language, in order of precedence: ours, then GitHub Linguist's.
+const LANGUAGE_ATTRIBUTES: &[&str] = &["report-todo-language", "linguist-language"];
+
+/// A line of a `.gitattributes` file setting or unsetting one of [`LANGUAGE_ATTRIBUTES`].
+struct Rule {
+    pattern: Gitignore,
+    attribute: &'static str,
+
+    /// `None` where the line unsets the attribute, e.g. `-linguist-language`.
+    value: Option<String>,
+}
+
+/// The language rules in one directory's `.gitattributes`.
+struct DirAttributes {
+    rules: Vec<Rule>,
+
+    /// Whether the directory is the top of a working tree, above which `.gitattributes` don't
+    /// apply.
+    is_work_tree: bool,
+}
+
+/// The language `path` is marked as by the `.gitattributes` in its directory or any above it, up
+/// to the top of its working tree. As in git, deeper files override shallower ones, and later
+/// lines earlier ones.
+pub fn language_attribute(path: &Path) -> Option<String> {
+    let path = absolute(path)?;
+
+    let mut dirs = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let attributes = dir_attributes(dir);
+        let is_work_tree = attributes.is_work_tree;
+        dirs.push(attributes);
+        if is_work_tree {
+            break;
+        }
+    }
+
+    LANGUAGE_ATTRIBUTES.iter().find_map(|attribute| {
+        dirs.iter()
+            .find_map(|dir| {
+                dir.rules.iter().rev().find(|rule| {
+                    rule.attribute == *attribute && rule.pattern.matched(&path, false).is_ignore()
+                })
+            })
+            .and_then(|rule| rule.value.clone())
+    })
+}
+
+/// `path` with its directory canonicalized, to match patterns against and find the directories
+/// above. `None` if the directory doesn't exist.
+fn absolute(path: &Path) -> Option<PathBuf> {
+    let file_name = path.file_name()?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Some(dir.canonicalize().ok()?.join(file_name))
+}
+
+/// Reads `dir`'s `.gitattributes`, once per directory.
+fn dir_attributes(dir: &Path) -> Arc<DirAttributes> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, Arc<DirAttributes>>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(attributes) = cache.lock().unwrap().get(dir) {
+        return attributes.clone();
+    }
+
+    let contents = std::fs::read_to_string(dir.join(".gitattributes")).unwrap_or_default();
+    let attributes = Arc::new(DirAttributes {
+        rules: contents
+            .lines()
+            .flat_map(|line| parse_line(dir, line))
+            .collect(),
+        is_work_tree: dir.join(".git").exists(),
+    });
+    cache
+        .lock()
+        .unwrap()
+        .insert(dir.to_owned(), attributes.clone());
+    attributes
+}
+
+/// The language rules on a line of `dir`'s `.gitattributes`. Comments, macro definitions and
+/// quoted patterns have none.
+fn parse_line(dir: &Path, line: &str) -> Vec<Rule> {
+    let mut fields = line.split_whitespace();
+    let pattern = match fields.next() {
+        Some(pattern) if !pattern.starts_with(['#', '[', '"']) => pattern,
+        _ => return Vec::new(),
+    };
+
+    let mut attributes = Vec::new();
+    for field in fields {
+        let (name, value) = match field.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_owned())),
+            None => match field.strip_prefix(['-', '!']) {
+                Some(name) => (name, None),
+                None => continue,
+            },
+        };
+        if let Some(attribute) = LANGUAGE_ATTRIBUTES
+            .iter()
+            .find(|attribute| **attribute == name)
+        {
+            attributes.push((*attribute, value));
+        }
+    }
+    if attributes.is_empty() {
+        return Vec::new();
+    }
+
+    let pattern = match GitignoreBuilder::new(dir)
+        .add_line(None, pattern)
+        .and_then(|builder| builder.build())
+    {
+        Ok(pattern) => pattern,
+        Err(_) => return Vec::new(),
+    };
+    attributes
+        .into_iter()
+        .map(|(attribute, value)| Rule {
+            pattern: pattern.clone(),
+            attribute,
+            value,
+        })
+        .collect()
+}
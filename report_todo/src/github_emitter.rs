@@ -0,0 +1,89 @@
+//! GitHub Actions workflow commands, which show findings as annotations on the pull request diff.
+
+use std::io::Write;
+
+use crate::{
+    emitter::Emitter,
+    json_emitter::Metadata,
+    paths::display_path,
+    todo_error::{Severity, TodoError},
+};
+
+/// `--format github`: one `::error` or `::warning` command per finding, which GitHub Actions picks
+/// up from stdout.
+pub struct GithubEmitter;
+
+impl Emitter for GithubEmitter {
+    fn write_to(
+        &self,
+        mut out: &mut dyn Write,
+        findings: &[TodoError],
+        _metadata: &Metadata,
+    ) -> anyhow::Result<()> {
+        Ok(write_commands(&mut out, findings)?)
+    }
+}
+
+/// Writes the workflow commands for `findings` to `out`. Untracked findings that fail the run are
+/// errors; everything else, including tracked findings reported with `--all`, are warnings.
+pub fn write_commands(out: &mut impl Write, findings: &[TodoError]) -> std::io::Result<()> {
+    for todo_error in findings {
+        let command = match todo_error.severity() {
+            Severity::Error => "error",
+            Severity::Warning | Severity::Info => "warning",
+        };
+        let path = display_path(todo_error.file_path());
+        writeln!(
+            out,
+            "::{} file={},line={},col={},title={}::{}",
+            command,
+            escape_property(path.strip_prefix("./").unwrap_or(&path)),
+            todo_error.row(),
+            todo_error.col(),
+            escape_property(todo_error.rule_id()),
+            escape_data(todo_error.message())
+        )?;
+    }
+    Ok(())
+}
+
+/// Keeps `text` from ending the command's message, which runs to the end of the line.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Keeps `text` from ending a `key=value` property early, too.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_per_severity() {
+        let mut out = Vec::new();
+        write_commands(
+            &mut out,
+            &[TodoError::sample(false), TodoError::sample(true)],
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "::error file=src/sample.rs,line=2,col=8,title=report-todo/todo/untracked::TODO \
+            found without issue number\n\
+            ::warning file=src/sample.rs,line=1,col=8,title=report-todo/todo/tracked::tracked \
+            sample\n"
+        );
+    }
+
+    #[test]
+    fn values_are_escaped() {
+        assert_eq!(escape_data("50% done\nmore"), "50%25 done%0Amore");
+        assert_eq!(escape_property("C:/a,b.rs"), "C%3A/a%2Cb.rs");
+    }
+}
@@ -0,0 +1,83 @@
+//! Filtering tracked findings by tracking id.
+
+use std::{ops::RangeInclusive, str::FromStr};
+
+use anyhow::anyhow;
+
+/// A tracking id, or an inclusive range of numeric ids, e.g. `9999` or `8000-8100`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdPattern {
+    Range(RangeInclusive<u64>),
+
+    /// Ids that aren't a numeric range are compared as text.
+    Literal(String),
+}
+
+impl IdPattern {
+    pub fn matches(&self, tracking_id: &str) -> bool {
+        match self {
+            IdPattern::Range(range) => tracking_id
+                .parse::<u64>()
+                .is_ok_and(|id| range.contains(&id)),
+            IdPattern::Literal(literal) => literal == tracking_id,
+        }
+    }
+}
+
+impl FromStr for IdPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(anyhow!("empty issue id"));
+        }
+
+        let bounds = s
+            .split_once('-')
+            .and_then(|(start, end)| Some((start.parse::<u64>().ok()?, end.parse::<u64>().ok()?)));
+        match bounds {
+            Some((start, end)) if start > end => {
+                Err(anyhow!("issue id range `{}` is backwards", s))
+            }
+            Some((start, end)) => Ok(IdPattern::Range(start..=end)),
+            None => Ok(IdPattern::Literal(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "9999".parse::<IdPattern>().unwrap(),
+            IdPattern::Literal("9999".to_owned())
+        );
+        assert_eq!(
+            "8000-8100".parse::<IdPattern>().unwrap(),
+            IdPattern::Range(8000..=8100)
+        );
+        assert_eq!(
+            "PROJ-12".parse::<IdPattern>().unwrap(),
+            IdPattern::Literal("PROJ-12".to_owned())
+        );
+        assert!("8100-8000".parse::<IdPattern>().is_err());
+        assert!("".parse::<IdPattern>().is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let range = IdPattern::Range(8000..=8100);
+        assert!(range.matches("8000"));
+        assert!(range.matches("8100"));
+        assert!(!range.matches("8101"));
+        assert!(!range.matches("PROJ-8050"));
+
+        let literal = IdPattern::Literal("PROJ-12".to_owned());
+        assert!(literal.matches("PROJ-12"));
+        assert!(!literal.matches("PROJ-123"));
+    }
+}
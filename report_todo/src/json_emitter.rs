@@ -0,0 +1,507 @@
+//! Structured output of findings for tools to consume.
+
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Context};
+use log::debug;
+use serde::Serialize;
+
+use crate::{
+    emitter::Emitter,
+    paths::{mangle, serialize_path},
+    todo_error::{Severity, TodoError},
+};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    pub issues_found: usize,
+    pub untracked: usize,
+    pub warnings: usize,
+    pub tracked: usize,
+    pub suppressed: usize,
+
+    /// Findings in `--third-party-dir`s, which aren't counted as untracked or tracked.
+    pub third_party: usize,
+}
+
+impl Summary {
+    /// Counts reported findings by severity. Suppressed findings are counted separately.
+    pub fn tally<'a>(findings: impl IntoIterator<Item = &'a TodoError>) -> Self {
+        let mut summary = Summary::default();
+        for todo_error in findings {
+            summary.issues_found += 1;
+            if todo_error.is_third_party() {
+                summary.third_party += 1;
+                continue;
+            }
+            match todo_error.severity() {
+                Severity::Error => summary.untracked += 1,
+                Severity::Warning => summary.warnings += 1,
+                Severity::Info => summary.tracked += 1,
+            }
+        }
+        summary
+    }
+}
+
+/// Which tool, config and revision produced a report, for comparing reports across time.
+#[derive(Debug, Serialize)]
+pub struct Metadata {
+    pub tool_version: &'static str,
+
+    /// When the report was written, in UTC, e.g. `2021-02-03T04:05:06Z`.
+    pub timestamp: String,
+
+    #[serde(serialize_with = "serialize_path")]
+    pub root: PathBuf,
+
+    /// The commit checked out in `root`, if it's in a git repository.
+    pub commit: Option<String>,
+
+    /// Hash of the effective config, which changes whenever a pattern or option does.
+    pub config_hash: String,
+}
+
+impl Metadata {
+    pub fn new(root: &Path, config_hash: String) -> Self {
+        let dir = if root.is_dir() {
+            root
+        } else {
+            root.parent().unwrap_or(root)
+        };
+        debug!("Running `git rev-parse HEAD` in {}", dir.display());
+        let commit = duct::cmd!("git", "rev-parse", "HEAD")
+            .dir(dir)
+            .stderr_null()
+            .read()
+            .ok()
+            .map(|commit| commit.trim().to_owned());
+
+        Metadata {
+            tool_version: env!("CARGO_PKG_VERSION"),
+            timestamp: utc_timestamp(SystemTime::now()),
+            root: root.to_owned(),
+            commit,
+            config_hash,
+        }
+    }
+}
+
+/// The UTC date of `time`, as `YYYY-MM-DD`.
+pub fn utc_date(time: SystemTime) -> String {
+    utc_timestamp(time)[.."YYYY-MM-DD".len()].to_owned()
+}
+
+/// Formats `time` as an RFC 3339 UTC timestamp, to the second.
+fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    let (days, seconds_of_day) = (seconds / 86400, seconds % 86400);
+
+    // Howard Hinnant's `civil_from_days`, for days since 1970-01-01.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Version of the report's layout. Field names and types don't change within a version.
+const REPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    version: u32,
+
+    /// `ok` if nothing was found, else `findings`, so that a clean run is distinguishable from
+    /// one that didn't produce a report.
+    status: &'static str,
+
+    /// Only in reports of a whole run, not in `--output-dir`'s per-file reports.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<&'a Metadata>,
+    findings: &'a [&'a TodoError],
+    summary: &'a Summary,
+}
+
+impl<'a> Report<'a> {
+    fn new(
+        findings: &'a [&'a TodoError],
+        summary: &'a Summary,
+        metadata: Option<&'a Metadata>,
+    ) -> Self {
+        Report {
+            version: REPORT_VERSION,
+            status: if findings.is_empty() {
+                "ok"
+            } else {
+                "findings"
+            },
+            metadata,
+            findings,
+            summary,
+        }
+    }
+
+    fn write_to(&self, mut writer: impl Write, pretty: bool) -> anyhow::Result<()> {
+        if pretty {
+            serde_json::to_writer_pretty(&mut writer, self)?;
+        } else {
+            serde_json::to_writer(&mut writer, self)?;
+        }
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
+/// `--format json` and `json-pretty`: all findings, the summary and metadata as one JSON document.
+pub struct JsonEmitter {
+    pub summary: Summary,
+
+    /// Indent the document.
+    pub pretty: bool,
+}
+
+impl Emitter for JsonEmitter {
+    fn write_to(
+        &self,
+        out: &mut dyn Write,
+        findings: &[TodoError],
+        metadata: &Metadata,
+    ) -> anyhow::Result<()> {
+        write_to(out, findings, &self.summary, metadata, self.pretty)
+    }
+
+    fn validate(&self, output: &str, findings: &[TodoError]) -> anyhow::Result<()> {
+        validate(output, findings.len()).context("invalid JSON report")
+    }
+
+    fn write_per_file(
+        &self,
+        findings: &[TodoError],
+        scanned: &[PathBuf],
+        output_dir: &Path,
+        empty_reports: bool,
+    ) -> anyhow::Result<()> {
+        write_per_file(findings, scanned, output_dir, empty_reports, self.pretty)
+    }
+}
+
+/// `--format ndjson`: one finding per line of JSON.
+pub struct NdjsonEmitter;
+
+impl Emitter for NdjsonEmitter {
+    fn write_to(
+        &self,
+        out: &mut dyn Write,
+        findings: &[TodoError],
+        _metadata: &Metadata,
+    ) -> anyhow::Result<()> {
+        write_lines(out, findings)
+    }
+
+    fn validate(&self, output: &str, findings: &[TodoError]) -> anyhow::Result<()> {
+        validate_lines(output, findings.len()).context("invalid JSON lines")
+    }
+}
+
+/// Writes all findings, the summary and `metadata` to `writer` as one JSON document, indented if
+/// `pretty`.
+pub fn write_to(
+    writer: impl Write,
+    findings: &[TodoError],
+    summary: &Summary,
+    metadata: &Metadata,
+    pretty: bool,
+) -> anyhow::Result<()> {
+    let findings = findings.iter().collect::<Vec<_>>();
+    Report::new(&findings, summary, Some(metadata)).write_to(writer, pretty)
+}
+
+/// Writes `todo_error` to `writer` as one line of JSON, as in the `findings` of a report.
+pub fn write_line(mut writer: impl Write, todo_error: &TodoError) -> anyhow::Result<()> {
+    serde_json::to_writer(&mut writer, todo_error)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Writes each finding to `writer` as one line of JSON.
+pub fn write_lines(mut writer: impl Write, findings: &[TodoError]) -> anyhow::Result<()> {
+    for todo_error in findings {
+        write_line(&mut writer, todo_error)?;
+    }
+    Ok(())
+}
+
+/// Checks that `report` is a single report of this version with `findings` findings, each with
+/// the fields every version guarantees.
+pub fn validate(report: &str, findings: usize) -> anyhow::Result<()> {
+    let report = serde_json::from_str::<serde_json::Value>(report)?;
+    if report["version"] != REPORT_VERSION {
+        return Err(anyhow!("expected version {}", REPORT_VERSION));
+    }
+    let status = if findings == 0 { "ok" } else { "findings" };
+    if report["status"] != status {
+        return Err(anyhow!("expected status `{}`", status));
+    }
+    if report["summary"]["issues_found"] != findings {
+        return Err(anyhow!("expected summary.issues_found to be {}", findings));
+    }
+
+    let reported = report["findings"]
+        .as_array()
+        .ok_or_else(|| anyhow!("expected a findings array"))?;
+    if reported.len() != findings {
+        return Err(anyhow!("expected {} findings", findings));
+    }
+    reported.iter().try_for_each(check_fields)
+}
+
+/// Checks that `lines` has one finding per line, `findings` in all, each with the fields every
+/// version guarantees.
+pub fn validate_lines(lines: &str, findings: usize) -> anyhow::Result<()> {
+    let reported = lines
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+    if reported.len() != findings {
+        return Err(anyhow!("expected {} findings", findings));
+    }
+    reported.iter().try_for_each(check_fields)
+}
+
+fn check_fields(finding: &serde_json::Value) -> anyhow::Result<()> {
+    for field in REQUIRED_FIELDS {
+        if finding.get(field).is_none() {
+            return Err(anyhow!("finding is missing `{}`", field));
+        }
+    }
+    Ok(())
+}
+
+/// Fields present in every finding, if only as `null`.
+const REQUIRED_FIELDS: &[&str] = &[
+    "tracking_id",
+    "severity",
+    "keyword",
+    "rule_id",
+    "id",
+    "fingerprint",
+    "file_path",
+    "row",
+    "col",
+    "message",
+];
+
+/// Writes one JSON document per source file into `output_dir`, named after the mangled source
+/// path. Scanned files without findings only get a report if `empty_reports` is set.
+pub fn write_per_file(
+    findings: &[TodoError],
+    scanned: &[PathBuf],
+    output_dir: &Path,
+    empty_reports: bool,
+    pretty: bool,
+) -> anyhow::Result<()> {
+    let mut by_file = BTreeMap::<&Path, Vec<&TodoError>>::new();
+    if empty_reports {
+        for path in scanned {
+            by_file.entry(path).or_default();
+        }
+    }
+    for todo_error in findings {
+        by_file
+            .entry(todo_error.file_path())
+            .or_default()
+            .push(todo_error);
+    }
+
+    std::fs::create_dir_all(output_dir)
+        .with_context(|| format!("failed to create {}", output_dir.display()))?;
+    for (path, findings) in by_file {
+        let report_path = output_dir.join(format!("{}.json", mangle(path)));
+        let file = std::io::BufWriter::new(
+            std::fs::File::create(&report_path)
+                .with_context(|| format!("failed to create {}", report_path.display()))?,
+        );
+        let summary = Summary::tally(findings.iter().copied());
+        Report::new(&findings, &summary, None).write_to(file, pretty)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use jsonschema::{Draft, JSONSchema};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::todo_error::Origin;
+
+    const SCHEMA: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/schemas/report.schema.json"
+    ));
+
+    /// Validates `value` against `schema`, returning the first error and where it is.
+    fn conforms(schema: &Value, value: &Value) -> Result<(), String> {
+        let schema = JSONSchema::options()
+            .with_draft(Draft::Draft202012)
+            .compile(schema)
+            .map_err(|error| error.to_string())?;
+        let result = schema.validate(value).map_err(|mut errors| {
+            let error = errors.next().unwrap();
+            format!("{}: {}", error.instance_path, error)
+        });
+        result
+    }
+
+    fn schema() -> Value {
+        serde_json::from_str(SCHEMA).unwrap()
+    }
+
+    /// The schema of one finding, as in an NDJSON line.
+    fn finding_schema() -> Value {
+        let schema = schema();
+        serde_json::json!({
+            "$schema": schema["$schema"],
+            "$defs": schema["$defs"],
+            "$ref": "#/$defs/finding",
+        })
+    }
+
+    fn report(findings: &[TodoError], pretty: bool) -> Value {
+        let mut report = Vec::new();
+        let metadata = Metadata::new(Path::new(env!("CARGO_MANIFEST_DIR")), "0".repeat(16));
+        write_to(
+            &mut report,
+            findings,
+            &Summary::tally(findings),
+            &metadata,
+            pretty,
+        )
+        .unwrap();
+        serde_json::from_slice(&report).unwrap()
+    }
+
+    #[test]
+    fn reports_match_schema() {
+        let schema = schema();
+        let mut notebook = TodoError::sample(false);
+        notebook.set_cell(3);
+        notebook.set_origin(Origin::FileExisting);
+        notebook.suppress("reason".to_owned());
+        notebook.embed_snippet();
+        let mut third_party = TodoError::sample(false);
+        third_party.mark_third_party();
+        let cases = [
+            Vec::new(),
+            vec![TodoError::sample(false)],
+            vec![TodoError::sample(true), TodoError::sample(false), notebook],
+            vec![third_party],
+        ];
+
+        for findings in &cases {
+            for &pretty in &[false, true] {
+                let report = report(findings, pretty);
+                assert_eq!(conforms(&schema, &report), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn status_marks_clean_runs() {
+        assert_eq!(report(&[], false)["status"], "ok");
+        assert_eq!(
+            report(&[TodoError::sample(true)], false)["status"],
+            "findings"
+        );
+    }
+
+    #[test]
+    fn third_party_findings_are_counted_separately() {
+        let mut third_party = TodoError::sample(false);
+        third_party.mark_third_party();
+        let summary = Summary::tally(&[TodoError::sample(false), third_party]);
+
+        assert_eq!(summary.issues_found, 2);
+        assert_eq!(summary.untracked, 1);
+        assert_eq!(summary.tracked, 0);
+        assert_eq!(summary.third_party, 1);
+    }
+
+    #[test]
+    fn utc_timestamps() {
+        let at = |seconds| utc_timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(at(1_612_325_106), "2021-02-03T04:05:06Z");
+        assert_eq!(at(4_107_542_399), "2100-02-28T23:59:59Z");
+        assert_eq!(
+            utc_date(SystemTime::UNIX_EPOCH + Duration::from_secs(1_612_325_106)),
+            "2021-02-03"
+        );
+    }
+
+    #[test]
+    fn finding_lines_match_schema() {
+        let schema = finding_schema();
+        let mut embedded = TodoError::sample(false);
+        embedded.embed_snippet();
+        for todo_error in &[TodoError::sample(false), TodoError::sample(true), embedded] {
+            let mut line = Vec::new();
+            write_line(&mut line, todo_error).unwrap();
+            let finding = serde_json::from_slice(&line).unwrap();
+            assert_eq!(conforms(&schema, &finding), Ok(()));
+        }
+    }
+
+    #[test]
+    fn schema_requires_guaranteed_fields() {
+        let schema = schema();
+        let required = schema["$defs"]["finding"]["required"].as_array().unwrap();
+        for field in REQUIRED_FIELDS {
+            assert!(required.contains(&Value::from(*field)), "{}", field);
+        }
+    }
+
+    #[test]
+    fn nonconforming_report_is_rejected() {
+        let schema = schema();
+        let mut report = report(&[TodoError::sample(true)], false);
+        report["findings"][0]["row"] = Value::from("1");
+
+        assert_eq!(
+            conforms(&schema, &report),
+            Err(r#"/findings/0/row: "1" is not of type "integer""#.to_owned())
+        );
+    }
+}
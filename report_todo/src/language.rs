@@ -0,0 +1,341 @@
+//! Detecting a source file's language.
+
+use std::{path::Path, sync::Arc};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::gitattributes;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Language {
+    C,
+    Cpp,
+    CSharp,
+    Css,
+    Go,
+    Haskell,
+    Html,
+    Java,
+    JavaScript,
+    Kotlin,
+    Lua,
+    Makefile,
+    Markdown,
+    Php,
+    Python,
+    Ruby,
+    Rust,
+    Shell,
+    Sql,
+    Swift,
+    Toml,
+    TypeScript,
+    Yaml,
+
+    /// A language defined in the config. Serialized by [`Language::name`] instead.
+    #[serde(skip_serializing)]
+    Custom(Arc<CustomLanguage>),
+}
+
+/// A language that isn't built in, defined in `report_todo.toml` as a `[[language]]` table, e.g.
+/// `extensions = ["dsl"]`, `line_comment = ";;"` and `block_comment = ["#|", "|#"]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CustomLanguage {
+    /// Reported as the finding's language. Defaults to the first extension.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// File extensions, without the `.`. Matched case-insensitively.
+    pub extensions: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_comment: Option<String>,
+
+    /// The delimiters starting and ending a block comment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_comment: Option<(String, String)>,
+}
+
+impl Language {
+    /// Detects the language from a `report-todo-language` or `linguist-language` attribute in
+    /// `.gitattributes`, or else `path`'s extension, trying `custom` languages before the built-in
+    /// ones.
+    pub fn detect(path: &Path, custom: &[Arc<CustomLanguage>]) -> Option<Language> {
+        if let Some(name) = gitattributes::language_attribute(path) {
+            match Language::from_name(&name, custom) {
+                Some(language) => return Some(language),
+                None => debug!(
+                    "unknown language {:?} in .gitattributes for {}",
+                    name,
+                    path.display()
+                ),
+            }
+        }
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        custom
+            .iter()
+            .find(|language| {
+                language
+                    .extensions
+                    .iter()
+                    .any(|custom| custom.eq_ignore_ascii_case(extension))
+            })
+            .map(|language| Language::Custom(language.clone()))
+            .or_else(|| Language::from_path(path))
+    }
+
+    /// The language called `name`, as GitHub Linguist names it (e.g. `C++`, `Shell`) or by a
+    /// `custom` language's name. Case-insensitive.
+    pub fn from_name(name: &str, custom: &[Arc<CustomLanguage>]) -> Option<Language> {
+        let custom = custom.iter().find(|language| {
+            Language::Custom(Arc::clone(language))
+                .name()
+                .is_some_and(|custom| custom.eq_ignore_ascii_case(name))
+        });
+        if let Some(language) = custom {
+            return Some(Language::Custom(language.clone()));
+        }
+
+        let language = match name.to_ascii_lowercase().as_str() {
+            "c" => Language::C,
+            "c++" | "cpp" => Language::Cpp,
+            "c#" | "csharp" => Language::CSharp,
+            "css" | "scss" => Language::Css,
+            "go" => Language::Go,
+            "haskell" => Language::Haskell,
+            "html" => Language::Html,
+            "java" => Language::Java,
+            "javascript" => Language::JavaScript,
+            "kotlin" => Language::Kotlin,
+            "lua" => Language::Lua,
+            "makefile" => Language::Makefile,
+            "markdown" => Language::Markdown,
+            "php" => Language::Php,
+            "python" => Language::Python,
+            "ruby" => Language::Ruby,
+            "rust" => Language::Rust,
+            "shell" | "bash" | "sh" | "zsh" => Language::Shell,
+            "sql" => Language::Sql,
+            "swift" => Language::Swift,
+            "toml" => Language::Toml,
+            "typescript" | "tsx" => Language::TypeScript,
+            "yaml" => Language::Yaml,
+            _ => return None,
+        };
+        Some(language)
+    }
+
+    /// Detects the language from `path`'s extension, or for some files, its name. Returns `None`
+    /// for anything unrecognized.
+    pub fn from_path(path: &Path) -> Option<Language> {
+        let file_name = path.file_name()?.to_str()?;
+        match file_name {
+            "Cargo.lock" => return Some(Language::Toml),
+            "Gemfile" | "Rakefile" => return Some(Language::Ruby),
+            "Makefile" | "makefile" | "GNUmakefile" => return Some(Language::Makefile),
+            _ => {}
+        }
+
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        let language = match extension.as_str() {
+            "c" | "h" => Language::C,
+            "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => Language::Cpp,
+            "cs" => Language::CSharp,
+            "css" | "scss" => Language::Css,
+            "go" => Language::Go,
+            "hs" => Language::Haskell,
+            "htm" | "html" => Language::Html,
+            "java" => Language::Java,
+            "js" | "jsx" | "mjs" | "cjs" => Language::JavaScript,
+            "kt" | "kts" => Language::Kotlin,
+            "lua" => Language::Lua,
+            "mk" => Language::Makefile,
+            "md" | "markdown" => Language::Markdown,
+            "php" => Language::Php,
+            "py" | "pyi" | "ipynb" => Language::Python,
+            "rb" => Language::Ruby,
+            "rs" => Language::Rust,
+            "sh" | "bash" | "zsh" => Language::Shell,
+            "sql" => Language::Sql,
+            "swift" => Language::Swift,
+            "toml" => Language::Toml,
+            "ts" | "tsx" => Language::TypeScript,
+            "yaml" | "yml" => Language::Yaml,
+            _ => return None,
+        };
+        Some(language)
+    }
+
+    /// The name a custom language is reported by, or `None` for a built-in one.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Language::Custom(language) => Some(
+                language
+                    .name
+                    .as_deref()
+                    .unwrap_or_else(|| language.extensions.first().map_or("", String::as_str)),
+            ),
+            _ => None,
+        }
+    }
+
+    /// The delimiter ending a block comment in this language, or `None` if it has no block
+    /// comments.
+    pub fn block_comment_end(&self) -> Option<&str> {
+        match self {
+            Language::C
+            | Language::Cpp
+            | Language::CSharp
+            | Language::Css
+            | Language::Go
+            | Language::Java
+            | Language::JavaScript
+            | Language::Kotlin
+            | Language::Php
+            | Language::Rust
+            | Language::Sql
+            | Language::Swift
+            | Language::TypeScript => Some("*/"),
+            Language::Html | Language::Markdown => Some("-->"),
+            Language::Haskell => Some("-}"),
+            Language::Lua => Some("]]"),
+            Language::Makefile
+            | Language::Python
+            | Language::Ruby
+            | Language::Shell
+            | Language::Toml
+            | Language::Yaml => None,
+            Language::Custom(language) => {
+                language.block_comment.as_ref().map(|(_, end)| end.as_str())
+            }
+        }
+    }
+
+    /// The delimiter starting a comment in this language: a line comment's, or a block comment's
+    /// where there are no line comments.
+    pub fn comment_delimiter(&self) -> Option<&str> {
+        match self {
+            Language::C
+            | Language::Cpp
+            | Language::CSharp
+            | Language::Go
+            | Language::Java
+            | Language::JavaScript
+            | Language::Kotlin
+            | Language::Php
+            | Language::Rust
+            | Language::Swift
+            | Language::TypeScript => Some("//"),
+            Language::Makefile
+            | Language::Python
+            | Language::Ruby
+            | Language::Shell
+            | Language::Toml
+            | Language::Yaml => Some("#"),
+            Language::Haskell | Language::Lua | Language::Sql => Some("--"),
+            Language::Css => Some("/*"),
+            Language::Html | Language::Markdown => Some("<!--"),
+            Language::Custom(language) => language.line_comment.as_deref().or_else(|| {
+                language
+                    .block_comment
+                    .as_ref()
+                    .map(|(start, _)| start.as_str())
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_path() {
+        assert_eq!(
+            Language::from_path(Path::new("src/main.rs")),
+            Some(Language::Rust)
+        );
+        assert_eq!(
+            Language::from_path(Path::new("./include/A.HPP")),
+            Some(Language::Cpp)
+        );
+        assert_eq!(
+            Language::from_path(Path::new("Cargo.lock")),
+            Some(Language::Toml)
+        );
+        assert_eq!(
+            Language::from_path(Path::new("build/Makefile")),
+            Some(Language::Makefile)
+        );
+        assert_eq!(Language::from_path(Path::new("README")), None);
+        assert_eq!(Language::from_path(Path::new("a.unknown")), None);
+    }
+
+    #[test]
+    fn custom_language() {
+        let dsl: CustomLanguage = toml::from_str(
+            r##"
+            extensions = ["dsl", "rs"]
+            line_comment = ";;"
+            block_comment = ["#|", "|#"]
+            "##,
+        )
+        .unwrap();
+        let custom = [Arc::new(dsl)];
+
+        let language = Language::detect(Path::new("src/a.DSL"), &custom).unwrap();
+        assert_eq!(language.name(), Some("dsl"));
+        assert_eq!(language.comment_delimiter(), Some(";;"));
+        assert_eq!(language.block_comment_end(), Some("|#"));
+        // Custom languages take precedence over built-in ones.
+        assert!(matches!(
+            Language::detect(Path::new("main.rs"), &custom),
+            Some(Language::Custom(_))
+        ));
+        assert_eq!(
+            Language::detect(Path::new("a.py"), &custom),
+            Some(Language::Python)
+        );
+        assert_eq!(Language::detect(Path::new("README"), &custom), None);
+    }
+
+    #[test]
+    fn gitattributes_override() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = temp_dir.path();
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        std::fs::create_dir_all(repo.join("vendor")).unwrap();
+        std::fs::write(
+            repo.join(".gitattributes"),
+            "# Templates\n\
+             *.inc linguist-language=PHP\n\
+             *.tpl linguist-language=HTML report-todo-language=dsl\n\
+             *.rs linguist-language=Klingon\n",
+        )
+        .unwrap();
+        std::fs::write(
+            repo.join("vendor/.gitattributes"),
+            "*.inc linguist-language=C++\nold.inc -linguist-language\n",
+        )
+        .unwrap();
+        let dsl: CustomLanguage = toml::from_str(r#"extensions = ["dsl"]"#).unwrap();
+        let custom = [Arc::new(dsl)];
+
+        let detect = |path: &str| Language::detect(&repo.join(path), &custom);
+        assert_eq!(detect("a.inc"), Some(Language::Php));
+        assert_eq!(detect("src/a.inc"), Some(Language::Php));
+        assert_eq!(detect("vendor/a.inc"), Some(Language::Cpp));
+        assert_eq!(detect("vendor/old.inc"), None);
+        assert!(matches!(detect("a.tpl"), Some(Language::Custom(_))));
+        // An unknown language falls back to the extension.
+        assert_eq!(detect("a.rs"), Some(Language::Rust));
+        assert_eq!(detect("a.py"), Some(Language::Python));
+    }
+}
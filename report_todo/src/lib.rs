@@ -0,0 +1,23 @@
+//! The scanners and report formats behind the `report_todo` command, also built as a library so
+//! the benchmarks can reach them.
+
+pub mod allow_list;
+pub mod blame;
+pub mod checkers;
+pub mod checkstyle_emitter;
+pub mod console_emitter;
+pub mod emitter;
+pub mod exec;
+pub mod gitattributes;
+pub mod github_emitter;
+pub mod issue_filter;
+pub mod json_emitter;
+pub mod language;
+pub mod notebook;
+pub mod paths;
+pub mod sarif_emitter;
+pub mod socket_emitter;
+pub mod step_summary_emitter;
+pub mod todo_error;
+#[cfg(feature = "tui")]
+pub mod tui;
@@ -1,31 +1,362 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use checkers::{
-    git_diff::GitDiffChecker, source_tree_simple::SourceTreeSimpleChecker,
-    source_tree_syntect::SourceTreeSyntectChecker, Checker,
+    diff::DiffChecker,
+    git_diff::GitDiffChecker,
+    locations::{load_locations, Location, LocationsChecker},
+    source_tree_simple::SourceTreeSimpleChecker,
+    source_tree_syntect::SourceTreeSyntectChecker,
+    Checker, ScanStats, SkipFiles,
 };
 use log::debug;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use structopt::StructOpt;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    ffi::OsString,
+    io::{IsTerminal, Read},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+use structopt::{clap::ArgMatches, StructOpt};
+use termcolor::ColorChoice;
+
+use report_todo::{
+    allow_list, blame, checkers, checkstyle_emitter, console_emitter, emitter, exec,
+    github_emitter, issue_filter, json_emitter, language, paths, sarif_emitter, socket_emitter,
+    step_summary_emitter, todo_error,
+};
 
-mod checkers;
-mod console_emitter;
-mod todo_error;
+use allow_list::{AllowList, ALLOW_LIST_FILE_NAME};
+use emitter::Emitter;
+use issue_filter::IdPattern;
+use language::CustomLanguage;
+use paths::{display_path, TestFiles};
+use todo_error::{
+    CaptureGroups, IssuePattern, LiteralPrefilter, Origin, Regexes, RustMacro, RustMacros,
+    Severity, SeverityMap, TodoError, ALLOW_MARKER,
+};
 
-use todo_error::Regexes;
+const DEFAULT_MATCH_ISSUE: &str = r"todo\(#(?P<issue_number>\d+)\):";
+const DEFAULT_TEST_PATTERNS: &str = "tests/,test/,__tests__/,*_test.rs,*_test.go,test_*.py,\
+    *_test.py,*.spec.ts,*.test.ts,*.spec.js,*.test.js,*Test.java,*Tests.cs";
 
 /// Will ignore files listed in `.todoignore` and `.gitignore`.
+///
+/// Options are also read from the nearest `report_todo.toml` at or above ROOT_DIR (or the current
+/// directory). Options passed on the command line take precedence over it.
+///
+/// With several ROOT_DIRs, or files, the options below that refer to ROOT_DIR mean the deepest
+/// directory containing them all.
+///
+/// Exits with 1 when the findings fail the run (see `--error-on`), and with 2 when the tool itself
+/// fails, e.g. on I/O errors, bad arguments or an invalid regex.
 #[derive(Debug, StructOpt)]
-struct Opt {
+struct Cli {
+    #[structopt(subcommand)]
+    command: Option<Command>,
+
+    /// Options for the deprecated invocation without a subcommand.
     #[structopt(flatten)]
-    config: Config,
+    opt: Opt,
 
-    /// Find issues only in the current branch. Assumes the branch is forked off the upstream remote HEAD.
+    /// Deprecated: use `report_todo diff`.
     #[structopt(long = "diff")]
     parse_diff: bool,
 
+    /// Deprecated: use `report_todo scan ROOT_DIR...`.
     #[structopt(name = "ROOT_DIR")]
-    root_dir: Option<PathBuf>,
+    root_dirs: Vec<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Find issues in source trees.
+    Scan {
+        #[structopt(flatten)]
+        opt: Opt,
+
+        /// Directories or files to scan. Defaults to the current directory.
+        #[structopt(name = "ROOT_DIR")]
+        root_dirs: Vec<PathBuf>,
+    },
+
+    /// Find issues only in lines changed on the current branch.
+    Diff {
+        #[structopt(flatten)]
+        opt: Opt,
+
+        /// Commit or other ref to diff against. Defaults to where the branch forked off the
+        /// upstream remote's HEAD.
+        #[structopt(name = "REF")]
+        base: Option<String>,
+
+        /// Read a unified diff from stdin instead of running `git diff`, e.g. from another version
+        /// control system.
+        #[structopt(long = "diff-stdin", conflicts_with = "REF")]
+        diff_stdin: bool,
+
+        /// Diff the staged changes against `HEAD`, i.e. what's about to be committed, for
+        /// pre-commit hooks.
+        #[structopt(long = "staged", conflicts_with_all = &["REF", "diff-stdin"])]
+        staged: bool,
+    },
+
+    /// Describe a rule id, as found in structured output, e.g. `report-todo/fixme/untracked`.
+    Explain {
+        #[structopt(name = "RULE")]
+        rule: String,
+    },
+
+    /// Work with configuration.
+    Config(ConfigCommand),
+}
+
+// Only ever parsed once, so the size of `Dump` and `Check` doesn't matter.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, StructOpt)]
+enum ConfigCommand {
+    /// Print the configuration given by the options and the nearest `report_todo.toml`, as TOML.
+    Dump {
+        #[structopt(flatten)]
+        config: Config,
+    },
+
+    /// Check the configuration given by the options and the nearest `report_todo.toml`: that its
+    /// patterns compile, that `--match-issue` captures the tracking id and that
+    /// `--issue-link-format` only refers to groups it defines. Fails if there are problems.
+    Check {
+        #[structopt(flatten)]
+        config: Config,
+    },
+
+    /// Write a commented starter `report_todo.toml` to the current directory.
+    Init {
+        /// Overwrite an existing `report_todo.toml`.
+        #[structopt(long = "force")]
+        force: bool,
+    },
+}
+
+const CONFIG_FILE_NAME: &str = "report_todo.toml";
+
+/// Comments written above each key by `config init`.
+const CONFIG_COMMENTS: &[(&str, &str)] = &[
+    (
+        "match_issue",
+        "Regex matching a tracked issue. The tracking id is captured by `id_group`.",
+    ),
+    (
+        "issue_link_format",
+        "Link to a tracked issue, with the captures of `match_issue` interpolated.",
+    ),
+    (
+        "forbidden_keywords",
+        "Keywords reported as errors unless they reference an issue.",
+    ),
+    (
+        "warn_keywords",
+        "Keywords reported as warnings, which don't fail the run.",
+    ),
+    (
+        "fuzzy",
+        "Also match keyword variants like `TODOs`, `TODO2` and `TODO_later`.",
+    ),
+    ("report_all", "Report tracked issues as well as untracked."),
+    ("skip_files", "File names to never report findings in."),
+    (
+        "fail_threshold",
+        "Number of untracked issues to tolerate before failing.",
+    ),
+    (
+        "error_on",
+        "Findings that fail the run: \"untracked\", \"any\" or \"never\".",
+    ),
+    (
+        "format",
+        "Output format: \"human\", \"json\", \"json-pretty\", \"ndjson\", \"github\", \
+        \"sarif\", \"checkstyle\" or \"step-summary\".",
+    ),
+];
+
+// Options shared by the `scan` and `diff` commands. Not a doc comment, as structopt would use it
+// as the description of the commands it's flattened into.
+#[derive(Debug, StructOpt)]
+struct Opt {
+    #[structopt(flatten)]
+    config: Config,
+
+    /// With `diff`, also scan the whole of each file changed on the branch. Findings outside the
+    /// changed lines are reported as warnings, which don't fail the run.
+    #[structopt(long = "touched-files")]
+    touched_files: bool,
+
+    /// With `diff`, also report tracked issues on removed lines, as their issue may be closeable.
+    /// These are informational and don't fail the run.
+    #[structopt(long = "report-removed")]
+    report_removed: bool,
+
+    /// When to color the human-readable output: `auto`, `always` or `never`. `auto` colors it
+    /// when stderr is a terminal, unless `$NO_COLOR` is set (and not empty) or `$TERM` is `dumb`.
+    #[structopt(
+        long = "color",
+        default_value = "auto",
+        possible_values = &["auto", "always", "never"],
+        parse(try_from_str = parse_color_choice)
+    )]
+    color: ColorChoice,
+
+    /// Print locations as absolute `file://` URLs, which some terminals make clickable.
+    #[structopt(long = "file-urls")]
+    file_urls: bool,
+
+    /// Cut source lines in reports down to this many characters around the finding. Defaults to
+    /// `$COLUMNS`, or 120 if that isn't set.
+    #[structopt(long = "max-width")]
+    max_width: Option<usize>,
+
+    /// Show at most N findings per file, summarizing the rest, so one pathological file doesn't
+    /// drown out the others. Findings are then ordered by file and location, with
+    /// `--untracked-first` putting untracked ones first within each file. Only the human-readable
+    /// output is cut short.
+    #[structopt(long = "max-per-file", value_name = "N")]
+    max_per_file: Option<usize>,
+
+    /// Print the help for untracked findings once, after all findings, rather than with each one.
+    /// Tracked findings' links are still shown with each.
+    #[structopt(long = "help-once")]
+    help_once: bool,
+
+    /// With `--all`, report untracked issues before tracked ones, each ordered by location.
+    #[structopt(long = "untracked-first")]
+    untracked_first: bool,
+
+    /// List findings suppressed with `report-todo:allow(reason)`, along with their reasons.
+    #[structopt(long = "show-suppressed")]
+    show_suppressed: bool,
+
+    /// Print a single debt score instead of reporting findings: `untracked * untracked-weight +
+    /// tracked * tracked-weight + overdue * overdue-weight`, counting every unsuppressed finding
+    /// regardless of `--all`. Overdue findings are tracked ones whose `--due-group` capture is a
+    /// `YYYY-MM-DD` date before today (UTC), and aren't counted as tracked too.
+    #[structopt(long = "score")]
+    score: bool,
+
+    /// With a structured `--format`, write one report per source file into this directory
+    /// instead of a single report to stdout.
+    #[structopt(long = "output-dir")]
+    output_dir: Option<PathBuf>,
+
+    /// In structured output (everything but `--format human` on stderr), report paths relative to
+    /// the root of the git repository containing ROOT_DIR, or to ROOT_DIR outside one, rather than
+    /// to the current directory. For uploading to dashboards that expect repository paths.
+    #[structopt(long = "repo-relative-paths")]
+    repo_relative_paths: bool,
+
+    /// Include each finding's line, trimmed, and where on it the finding is, in `json`,
+    /// `json-pretty`, `ndjson` and `sarif` output, so the report can be read without the source.
+    /// Other formats have no place for it.
+    #[structopt(long = "embed-snippet")]
+    embed_snippet: bool,
+
+    /// Also write the findings to a file in another format, as `FORMAT:PATH`, e.g.
+    /// `json:findings.json`. `human` output is written without colors. Can be given more than
+    /// once. The tree is only scanned once.
+    #[structopt(long = "also", number_of_values = 1)]
+    also: Vec<AlsoOutput>,
+
+    /// Stream findings to this Unix socket as JSON, one per line, instead of writing `--format`
+    /// output to stdout.
+    #[structopt(long = "emit-socket", value_name = "PATH", conflicts_with = "emit-tcp")]
+    emit_socket: Option<PathBuf>,
+
+    /// Stream findings to this TCP address, e.g. `127.0.0.1:9000`, as JSON, one per line, instead
+    /// of writing `--format` output to stdout.
+    #[structopt(long = "emit-tcp", value_name = "ADDRESS")]
+    emit_tcp: Option<String>,
+
+    /// Run this command for each untracked finding reported, e.g. `notify {file} {line} {message}`.
+    /// `{file}`, `{line}`, `{col}`, `{message}`, `{keyword}` and `{rule}` are filled in. Arguments
+    /// are split on whitespace and the command is run without a shell. The run fails if any
+    /// command does.
+    #[structopt(long = "exec")]
+    exec: Option<String>,
+
+    /// Number of `--exec` commands to run at once. Defaults to the number of CPUs.
+    #[structopt(long = "exec-jobs")]
+    exec_jobs: Option<usize>,
+
+    /// Print the `--exec` commands instead of running them.
+    #[structopt(long = "exec-dry-run")]
+    exec_dry_run: bool,
+
+    /// With `--output-dir`, also write (empty) reports for scanned files without findings.
+    #[structopt(long = "empty-reports")]
+    empty_reports: bool,
+
+    /// Only scan files modified since the last `--since-mtime` run on ROOT_DIR that passed, as
+    /// recorded in the repository's git directory, or outside one, in the user's cache directory.
+    /// Findings in other files are not reported, so this is a fast
+    /// incremental check rather than a full audit.
+    #[structopt(long = "since-mtime")]
+    since_mtime: bool,
+
+    /// Don't descend more than N directories below ROOT_DIR when scanning a source tree. 1 scans
+    /// only ROOT_DIR's direct entries.
+    #[structopt(long = "depth", value_name = "N")]
+    depth: Option<usize>,
+
+    /// Print a terse `PASS`/`FAIL` verdict per scanned file instead of reporting findings, for
+    /// pre-commit hooks. Typically given the staged files as `ROOT_DIR`s.
+    #[structopt(long = "precommit")]
+    precommit: bool,
+
+    /// With `--format human`, print `report_todo: clean` to stdout when nothing is found, so a
+    /// pipeline can tell a clean run from one that produced no output. JSON reports always say so
+    /// in their `status`.
+    #[structopt(long = "success-marker")]
+    success_marker: bool,
+
+    /// Only scan the lines listed in this JSON file, an array of `{"path": ..., "line": ...}`
+    /// objects, and print whether each still has an untracked issue (`UNTRACKED`) or not
+    /// (`RESOLVED`) instead of reporting findings. Re-checks previous findings after a fix without
+    /// a full scan. Only the listed files are read, so ROOT_DIR is ignored.
+    #[structopt(long = "only-locations", value_name = "FILE")]
+    only_locations: Option<PathBuf>,
+
+    /// Only report findings on lines last changed at or before the commit of this tag or other git
+    /// ref, i.e. debt that's lingered since then. Uncommitted lines and untracked files are left
+    /// out.
+    #[structopt(long = "older-than")]
+    older_than: Option<String>,
+
+    /// Run the checker but discard findings, reporting scan throughput instead.
+    #[structopt(long = "dry-parse")]
+    dry_parse: bool,
+
+    /// Read files larger than this many bytes a line at a time instead of all at once, so huge
+    /// generated files don't exhaust memory. Has no effect with `--use-syntect`, which needs whole
+    /// files to follow block comments across lines.
+    #[structopt(long = "stream-above")]
+    stream_above: Option<u64>,
+
+    /// After reporting, print the number of findings under each directory, truncated to this many
+    /// path components. e.g. with `2`, findings in `src/api/v1` are counted under `src/api`.
+    #[structopt(long = "rollup-depth")]
+    rollup_depth: Option<usize>,
+
+    /// Check the config and that `--format` produces valid output for a couple of made-up
+    /// findings, print `OK`, and exit without scanning.
+    #[structopt(long = "selftest")]
+    selftest: bool,
+
+    /// Browse the findings in the terminal instead of writing `--format` output: arrow through
+    /// them grouped by file, press `o` to open a finding's issue link in the browser, or `e` to
+    /// open its file at its line in `$VISUAL` or `$EDITOR`. Requires building with `--features
+    /// tui`.
+    #[structopt(long = "tui", conflicts_with_all = &["output-dir", "emit-socket", "emit-tcp"])]
+    tui: bool,
 
     /// Use syntect to parse just comments.
     #[structopt(long, hidden = true)]
@@ -37,38 +368,405 @@ struct Opt {
 // TODO(#6): find todo by tracking number
 #[derive(Debug, StructOpt, Serialize, Deserialize)]
 struct Config {
-    /// Regex to detect an issue with tracking idenfitied (i.e. GitHub issue number).
+    /// Regex to detect an issue with tracking idenfitied (i.e. GitHub issue number). Repeat for
+    /// several trackers' conventions, e.g. `todo\(#(?P<issue_number>\d+)\):` and
+    /// `todo\((?P<issue_number>JIRA-\d+)\):`; the first to match is used.
     #[structopt(
         long = "match-issue",
-        default_value = r"todo\(#(?P<issue_number>\d+)\):"
+        default_value = DEFAULT_MATCH_ISSUE,
+        number_of_values = 1
     )]
-    match_issue: String,
+    #[serde(deserialize_with = "one_or_many")]
+    match_issue: Vec<String>,
+
+    /// Name of the capture group in `--match-issue` holding the tracking id. If the pattern
+    /// doesn't define it, the first capture is used.
+    #[structopt(long = "id-group", default_value = "issue_number")]
+    id_group: String,
+
+    /// Name of the capture group in `--match-issue` holding who the issue is assigned to.
+    #[structopt(long = "assignee-group", default_value = "assignee")]
+    assignee_group: String,
+
+    /// Name of the capture group in `--match-issue` holding when the issue is due.
+    #[structopt(long = "due-group", default_value = "due")]
+    due_group: String,
+
+    /// Name of the capture group in `--match-issue` holding the project the issue belongs to.
+    #[structopt(long = "project-group", default_value = "project")]
+    project_group: String,
 
     /// Regex replace string used to format the output link. e.g. `https://github.com/tangmi/report_todo/issues/${issue_number}`
-    #[structopt(long = "issue-link-format")]
-    issue_link_format: Option<String>,
+    /// Give one per `--match-issue`, in the same order, or one for all of them.
+    #[structopt(long = "issue-link-format", number_of_values = 1)]
+    #[serde(default, deserialize_with = "one_or_many")]
+    issue_link_format: Vec<String>,
+
+    /// Regex to detect an issue referenced by URL, with the tracking id as the first capture. A
+    /// forbidden keyword on the same line is then treated as tracked. e.g.
+    /// `https://github\.com/tangmi/report_todo/issues/(?P<issue_number>\d+)`
+    #[structopt(long = "match-issue-url")]
+    match_issue_url: Option<String>,
 
     /// Expected to match `\w+`.
     #[structopt(long = "forbid", default_value = "todo")]
     forbidden_keywords: Vec<String>,
 
+    /// Keywords to report as errors, in addition to `--forbid`. Comma-separated.
+    #[structopt(long = "error-keywords", use_delimiter = true, number_of_values = 1)]
+    error_keywords: Vec<String>,
+
+    /// Keywords to report as warnings, which don't fail the run. Takes precedence over `--forbid`
+    /// and `--error-keywords`. Comma-separated.
+    #[structopt(long = "warn-keywords", use_delimiter = true, number_of_values = 1)]
+    warn_keywords: Vec<String>,
+
     /// Report tracked issues as well as untracked.
+    ///
+    /// Reporting modes: by default only untracked issues are reported; `--all` reports both; and
+    /// `--only-tracked` reports just tracked issues. `--only-tracked` is informational and never
+    /// fails the run.
     #[structopt(long = "all")]
     report_all: bool,
+
+    /// Report only tracked issues, e.g. to cross-check the issues referenced in code against the
+    /// tracker. Conflicts with `--all`.
+    #[structopt(long = "only-tracked", conflicts_with = "report-all")]
+    only_tracked: bool,
+
+    /// Tracking ids to leave out of `--all` output, as ids or inclusive numeric ranges. e.g.
+    /// `9999,8000-8100`. Comma-separated.
+    #[structopt(long = "hide-issues", use_delimiter = true, number_of_values = 1)]
+    hide_issues: Vec<String>,
+
+    /// File names to never report findings in, wherever they are. Comma-separated.
+    #[structopt(
+        long = "skip-files",
+        default_value = "Cargo.lock,package-lock.json",
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    skip_files: Vec<String>,
+
+    /// Also scan files marked `@generated` in their first few lines, which are skipped by default.
+    #[structopt(long = "scan-generated")]
+    scan_generated: bool,
+
+    /// Also scan git submodules and other nested working trees, which are skipped by default as
+    /// they're usually third-party code.
+    #[structopt(long = "scan-submodules")]
+    scan_submodules: bool,
+
+    /// Skip git submodules and other nested working trees. This is the default.
+    #[structopt(long = "skip-submodules", conflicts_with = "scan-submodules")]
+    skip_submodules: bool,
+
+    /// How to report Rust's `todo!()`, `unimplemented!()` and `unreachable!()` (or other) macros
+    /// in `.rs` files, as `name=level` where `level` is `error`, `warning` or `ignore`.
+    /// Comma-separated.
+    #[structopt(
+        long = "rust-macros",
+        default_value = "todo=error,unimplemented=warning,unreachable=ignore",
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    rust_macros: Vec<String>,
+
+    /// Also match common variants of each keyword, e.g. for `todo`: plurals (`TODOs`), numbered
+    /// keywords (`TODO2`) and keywords joined to an identifier (`TODO_later`). Punctuated forms
+    /// (`TODO:`, `TODO -`, `@TODO`) match either way. Variants are reported as the keyword.
+    #[structopt(long = "fuzzy")]
+    fuzzy: bool,
+
+    /// Note style problems with each finding's comment: trailing whitespace, tab indentation, and
+    /// no space between the comment delimiter and the keyword.
+    #[structopt(long = "style-checks")]
+    style_checks: bool,
+
+    /// Severity labels to report findings with in JSON output, as `state=label` where `state` is
+    /// `untracked`, `warning`, `tracked` or `overdue`, e.g. `untracked=error,tracked=note`.
+    /// Comma-separated. Unmapped states are reported as `error`, `warning` and `info`
+    /// respectively; `overdue` findings are tracked ones due before today (see `--score`), and
+    /// keep the `tracked` label unless it's mapped.
+    #[structopt(long = "severity-map", use_delimiter = true, number_of_values = 1)]
+    severity_map: Vec<String>,
+
+    /// Directories under ROOT_DIR holding third-party code, e.g. `vendor,third_party`.
+    /// Comma-separated. Findings in them are informational: they never fail the run, and are only
+    /// reported with `--all`, though they're always counted.
+    #[structopt(long = "third-party-dir", use_delimiter = true, number_of_values = 1)]
+    third_party_dirs: Vec<String>,
+
+    /// Report untracked findings in test files as warnings, so they never fail the run.
+    #[structopt(long = "exclude-tests")]
+    exclude_tests: bool,
+
+    /// `.gitignore`-style patterns for the test files `--exclude-tests` applies to, relative to
+    /// ROOT_DIR. Comma-separated.
+    #[structopt(
+        long = "test-patterns",
+        default_value = DEFAULT_TEST_PATTERNS,
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    test_patterns: Vec<String>,
+
+    /// Also try `--match-issue` against the lines following a keyword in the same comment, for
+    /// issue references wrapped onto a continuation line. Only applies to comment-aware scanning.
+    #[structopt(long = "match-wrapped")]
+    match_wrapped: bool,
+
+    /// Report at most one finding per line, the leftmost, even if several keywords or macros
+    /// match it. By default each keyword and macro is reported once per line, at its first
+    /// occurrence.
+    #[structopt(long = "first-match-only")]
+    first_match_only: bool,
+
+    /// Number of threads to scan with. Defaults to all CPUs but two, and is at least one.
+    #[structopt(long = "threads")]
+    threads: Option<usize>,
+
+    /// Number of untracked issues to tolerate before failing.
+    #[structopt(long = "fail-threshold", default_value = "0")]
+    fail_threshold: usize,
+
+    /// Findings that fail the run, with exit code 1: `untracked`, `any` (tracked issues and
+    /// warnings, too) or `never`. `--fail-threshold` applies to the findings counted.
+    #[structopt(long = "error-on", default_value = "untracked")]
+    error_on: ErrorOn,
+
+    /// Weight of each untracked issue in `--score`.
+    #[structopt(long = "untracked-weight", default_value = "5")]
+    untracked_weight: f64,
+
+    /// Weight of each tracked issue in `--score`.
+    #[structopt(long = "tracked-weight", default_value = "1")]
+    tracked_weight: f64,
+
+    /// Weight of each tracked issue past its due date in `--score`.
+    #[structopt(long = "overdue-weight", default_value = "10")]
+    overdue_weight: f64,
+
+    /// Output format: `human`, `json`, `json-pretty`, `ndjson`, `github`, `sarif`, `checkstyle`
+    /// or `step-summary`.
+    #[structopt(long = "format", default_value = "human")]
+    format: Format,
+
+    /// Languages to recognize beyond the built-in ones, with their comment delimiters. Only read
+    /// from `report_todo.toml`, as `[[language]]` tables; see [`CustomLanguage`]. Last, as TOML
+    /// needs tables after plain values.
+    #[structopt(skip)]
+    #[serde(default, rename = "language", skip_serializing_if = "Vec::is_empty")]
+    languages: Vec<CustomLanguage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Format {
+    /// Colored snippets on stderr.
+    Human,
+
+    /// All findings and a summary as one JSON object on stdout:
+    /// `{"version":1,"findings":[...],"summary":{...}}`.
+    Json,
+
+    /// As `json`, but indented.
+    JsonPretty,
+
+    /// One finding per line of JSON on stdout, as in the `findings` of a `json` report, without a
+    /// summary. For editors and other tools that read findings as a stream.
+    Ndjson,
+
+    /// GitHub Actions `::error` and `::warning` workflow commands on stdout, which annotate the
+    /// findings' lines in pull requests.
+    Github,
+
+    /// A SARIF 2.1.0 log with one result per finding on stdout, for code scanning uploads.
+    Sarif,
+
+    /// Checkstyle XML on stdout, with a `<file>` per file with findings, for CI warnings plugins.
+    Checkstyle,
+
+    /// A markdown table of findings and the totals, appended to `$GITHUB_STEP_SUMMARY` (or
+    /// written to stdout if it isn't set) for the GitHub Actions run page.
+    StepSummary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum ErrorOn {
+    /// Untracked issues, except those reported as warnings.
+    Untracked,
+
+    /// Every reported finding.
+    Any,
+
+    /// Nothing; findings are only reported.
+    Never,
+}
+
+impl FromStr for ErrorOn {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "untracked" => Ok(ErrorOn::Untracked),
+            "any" => Ok(ErrorOn::Any),
+            "never" => Ok(ErrorOn::Never),
+            _ => Err(anyhow!("unknown --error-on level: {}", s)),
+        }
+    }
+}
+
+/// Findings that fail the run, as opposed to errors of the tool itself, which exit with a
+/// different code.
+#[derive(Debug)]
+struct PolicyViolation(String);
+
+impl std::fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+fn parse_color_choice(s: &str) -> anyhow::Result<ColorChoice> {
+    match s {
+        "auto" => Ok(ColorChoice::Auto),
+        "always" => Ok(ColorChoice::Always),
+        "never" => Ok(ColorChoice::Never),
+        _ => Err(anyhow!("unknown --color choice: {}", s)),
+    }
+}
+
+/// `--color`, with `auto` resolved here rather than by termcolor: it only checks `$TERM` and
+/// `$NO_COLOR`, not whether the output is a terminal, and turns colors off for an empty
+/// `$NO_COLOR`, which https://no-color.org says to ignore. `var` reads an environment variable.
+fn color_choice(
+    color: ColorChoice,
+    is_terminal: bool,
+    var: impl Fn(&str) -> Option<OsString>,
+) -> ColorChoice {
+    if color != ColorChoice::Auto {
+        return color;
+    }
+
+    let no_color = var("NO_COLOR").is_some_and(|no_color| !no_color.is_empty());
+    let dumb = var("TERM").is_some_and(|term| term == "dumb");
+    if is_terminal && !no_color && !dumb {
+        ColorChoice::Always
+    } else {
+        ColorChoice::Never
+    }
+}
+
+/// Where `diff` gets the changed lines from.
+#[derive(Debug)]
+enum DiffSource {
+    /// `git diff` against this ref, or the fork point.
+    Git(Option<String>),
+    /// `git diff --cached`.
+    Staged,
+    Stdin,
+}
+
+/// A `--format` written to a file, in addition to the main output.
+#[derive(Debug)]
+struct AlsoOutput {
+    format: Format,
+    path: PathBuf,
+}
+
+impl AlsoOutput {
+    fn write(
+        &self,
+        findings: &[TodoError],
+        summary: &json_emitter::Summary,
+        metadata: &json_emitter::Metadata,
+    ) -> anyhow::Result<()> {
+        let file = std::fs::File::create(&self.path)
+            .with_context(|| format!("failed to create {}", self.path.display()))?;
+        let mut file = std::io::BufWriter::new(file);
+        self.format
+            .emitter(summary)
+            .write_to(&mut file, findings, metadata)?;
+        std::io::Write::flush(&mut file)?;
+        Ok(())
+    }
+}
+
+impl FromStr for AlsoOutput {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (format, path) = s
+            .split_once(':')
+            .filter(|(_, path)| !path.is_empty())
+            .ok_or_else(|| anyhow!("expected `FORMAT:PATH`, got `{}`", s))?;
+        Ok(AlsoOutput {
+            format: format.parse()?,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+impl Format {
+    /// The emitter writing this format. `summary` is the run's, for the formats that include it.
+    fn emitter(self, summary: &json_emitter::Summary) -> Box<dyn Emitter> {
+        match self {
+            Format::Human => Box::new(console_emitter::HumanEmitter),
+            Format::Json | Format::JsonPretty => Box::new(json_emitter::JsonEmitter {
+                summary: summary.clone(),
+                pretty: self == Format::JsonPretty,
+            }),
+            Format::Ndjson => Box::new(json_emitter::NdjsonEmitter),
+            Format::Github => Box::new(github_emitter::GithubEmitter),
+            Format::Sarif => Box::new(sarif_emitter::SarifEmitter),
+            Format::Checkstyle => Box::new(checkstyle_emitter::CheckstyleEmitter),
+            Format::StepSummary => Box::new(step_summary_emitter::StepSummaryEmitter {
+                summary: summary.clone(),
+            }),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(Format::Human),
+            "json" => Ok(Format::Json),
+            "json-pretty" => Ok(Format::JsonPretty),
+            "ndjson" => Ok(Format::Ndjson),
+            "github" => Ok(Format::Github),
+            "sarif" => Ok(Format::Sarif),
+            "checkstyle" => Ok(Format::Checkstyle),
+            "step-summary" => Ok(Format::StepSummary),
+            _ => Err(anyhow!("unknown format: {}", s)),
+        }
+    }
 }
 
-fn main() -> anyhow::Result<()> {
-    // TODO(#6): try finding a config file in current directory first
-    let opt = Opt::from_args();
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("Error: {:?}", error);
+        std::process::exit(if error.is::<PolicyViolation>() { 1 } else { 2 });
+    }
+}
 
-    // let opt = Opt {
-    //     // Global conifg? `dirs::config_dir`?
-    //     config: toml::from_str(&std::fs::read_to_string(concat!(
-    //         env!("CARGO_MANIFEST_DIR"),
-    //         "/report_todo.toml"
-    //     ))?)?,
-    //     root_dir: PathBuf::from("."),
-    // };
+fn run() -> anyhow::Result<()> {
+    let matches = Cli::clap().get_matches_safe().unwrap_or_else(|error| {
+        // `--help` and `--version` are "errors" too, which exit successfully.
+        if error.use_stderr() {
+            eprintln!("{}", error.message);
+            std::process::exit(2);
+        }
+        error.exit()
+    });
+    let cli = Cli::from_clap(&matches);
+    let config_matches = innermost_subcommand(&matches);
 
     if cfg!(debug_assertions) {
         env_logger::builder()
@@ -78,71 +776,1505 @@ fn main() -> anyhow::Result<()> {
         env_logger::init();
     }
 
-    let regexes = Regexes {
-        match_issue: regex::RegexBuilder::new(&format!(r"\b{}", opt.config.match_issue))
-            .case_insensitive(true)
-            .build()?,
-        issue_link_format: opt.config.issue_link_format.clone(),
-        bad_keywords: opt
-            .config
-            .forbidden_keywords
-            .iter()
-            .map(|keyword| {
-                regex::RegexBuilder::new(&format!(r"\b{}\b", keyword))
-                    .case_insensitive(true)
-                    .build()
-            })
-            .collect::<Result<Vec<_>, _>>()?,
+    let (mut opt, root_dirs, diff_base) = match cli.command {
+        Some(Command::Scan { opt, root_dirs }) => (opt, root_dirs, None),
+        Some(Command::Diff {
+            opt,
+            base,
+            diff_stdin,
+            staged,
+        }) => {
+            let source = if diff_stdin {
+                DiffSource::Stdin
+            } else if staged {
+                DiffSource::Staged
+            } else {
+                DiffSource::Git(base)
+            };
+            (opt, Vec::new(), Some(source))
+        }
+        Some(Command::Explain { rule }) => return explain(&rule),
+        Some(Command::Config(ConfigCommand::Dump { config })) => {
+            let config = load_config(config, Path::new("."), config_matches)?;
+            print!("{}", toml::to_string(&config)?);
+            return Ok(());
+        }
+        Some(Command::Config(ConfigCommand::Init { force })) => return config_init(force),
+        Some(Command::Config(ConfigCommand::Check { config })) => {
+            return config_check(&load_config(config, Path::new("."), config_matches)?)
+        }
+        None => {
+            eprintln!(
+                "warning: running without a subcommand is deprecated; use `report_todo scan` or \
+                `report_todo diff`"
+            );
+            let diff_base = if cli.parse_diff {
+                Some(DiffSource::Git(None))
+            } else {
+                None
+            };
+            (cli.opt, cli.root_dirs, diff_base)
+        }
+    };
+
+    if opt.tui && !cfg!(feature = "tui") {
+        return Err(anyhow!(TUI_UNAVAILABLE));
+    }
+
+    let roots = if root_dirs.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        root_dirs
+    };
+    if opt.only_locations.is_none() {
+        if let Some(root) = roots.iter().find(|root| !root.exists()) {
+            return Err(anyhow!("no such file or directory: {}", root.display()));
+        }
+    }
+    let root_dir = paths::common_dir(&roots);
+    debug!("using {} for the config and state", root_dir.display());
+    opt.config = load_config(opt.config, &root_dir, config_matches)?;
+
+    let regexes = build_regexes(&opt.config)?;
+    let severity_map =
+        SeverityMap::parse(&opt.config.severity_map).context("invalid --severity-map")?;
+    let test_files = if opt.config.exclude_tests {
+        Some(TestFiles::new(&opt.config.test_patterns).context("invalid --test-patterns")?)
+    } else {
+        None
     };
+    debug!("literal keyword prefilter: {}", regexes.prefilter.is_some());
+
+    if opt.selftest {
+        return selftest(opt.config.format);
+    }
 
-    let mut stderr = console_emitter::ColoredWriter::new();
+    let mut stderr = console_emitter::ColoredWriter::stderr(color_choice(
+        opt.color,
+        std::io::stderr().is_terminal(),
+        |name| std::env::var_os(name),
+    ));
+    stderr.file_urls = opt.file_urls;
+    stderr.help_once = opt.help_once;
+    stderr.max_width = opt
+        .max_width
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+        .unwrap_or(console_emitter::DEFAULT_MAX_WIDTH);
 
     let mut issues_found_count = 0_usize;
-    let mut has_untracked = false;
+    let mut untracked_count = 0_usize;
+    let mut warning_count = 0_usize;
+    let mut third_party_reported_count = 0_usize;
+    let mut reported = Vec::new();
+    let mut rollup = BTreeMap::<String, usize>::new();
 
     let config = opt.config;
 
-    let checker: Box<dyn Checker> = if opt.parse_diff {
-        Box::new(GitDiffChecker {})
+    let hidden_issues = config
+        .hide_issues
+        .iter()
+        .map(|pattern| pattern.parse::<IdPattern>())
+        .collect::<anyhow::Result<Vec<_>>>()
+        .context("invalid `--hide-issues`")?;
+
+    let run_started = SystemTime::now();
+    let last_run = if opt.since_mtime {
+        Some(last_run_path(&root_dir)?)
+    } else {
+        None
+    };
+    let modified_since = match &last_run {
+        Some(last_run) => read_last_run(last_run)?,
+        None => None,
+    };
+    // Recorded only once the run has passed, so that files with failing findings are scanned
+    // again.
+    let passed = |result: anyhow::Result<()>| match (&result, &last_run) {
+        (Ok(()), Some(last_run)) => write_last_run(last_run, run_started),
+        _ => result,
+    };
+
+    let skip_files = SkipFiles {
+        names: config.skip_files.clone(),
+        generated: !config.scan_generated,
+        submodules: !config.scan_submodules,
+    };
+
+    let only_locations = opt
+        .only_locations
+        .as_deref()
+        .map(load_locations)
+        .transpose()?;
+    if only_locations.is_some() && diff_base.is_some() {
+        return Err(anyhow!("--only-locations can't be used with `diff`"));
+    }
+
+    let checker: Box<dyn Checker> = if let Some(locations) = &only_locations {
+        Box::new(LocationsChecker {
+            locations: locations.clone(),
+        })
+    } else if let Some(source) = &diff_base {
+        match source {
+            DiffSource::Git(base) => Box::new(GitDiffChecker {
+                base: base.clone(),
+                staged: false,
+                report_removed: opt.report_removed,
+                skip: skip_files.clone(),
+            }),
+            DiffSource::Staged => Box::new(GitDiffChecker {
+                base: None,
+                staged: true,
+                report_removed: opt.report_removed,
+                skip: skip_files.clone(),
+            }),
+            DiffSource::Stdin => {
+                let mut diff = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut diff)
+                    .context("failed to read a diff from stdin")?;
+                Box::new(DiffChecker {
+                    diff,
+                    root: PathBuf::from("."),
+                    report_removed: opt.report_removed,
+                    skip: skip_files.clone(),
+                })
+            }
+        }
     } else {
         if opt.use_syntect {
             debug!("using syntect-based source tree checker");
             Box::new(SourceTreeSyntectChecker {
-                root_dir: opt.root_dir.unwrap_or(PathBuf::from(".")),
+                roots,
+                modified_since,
+                max_depth: opt.depth,
+                threads: config.threads,
+                skip: skip_files.clone(),
             })
         } else {
             Box::new(SourceTreeSimpleChecker {
-                root_dir: opt.root_dir.unwrap_or(PathBuf::from(".")),
+                roots,
+                modified_since,
+                max_depth: opt.depth,
+                stream_above: opt.stream_above,
+                threads: config.threads,
+                skip: skip_files.clone(),
             })
         }
     };
 
-    for todo_error in checker
-        .process_spans(&regexes)?
-        .into_iter()
-        .filter(|todo_error| {
-            if !todo_error.is_tracked() || (config.report_all && todo_error.is_tracked()) {
-                true
-            } else {
-                false
+    let stats = ScanStats::default();
+    let start = Instant::now();
+    let mut todo_errors = checker.process_spans(&regexes, &stats)?;
+
+    if diff_base.is_some() && opt.touched_files {
+        scan_touched_files(&mut todo_errors, &regexes, &stats, &skip_files)?;
+    }
+
+    if opt.dry_parse {
+        let seconds = start.elapsed().as_secs_f64();
+        let megabytes = stats.bytes() as f64 / (1024.0 * 1024.0);
+        eprintln!(
+            "scanned {} files ({:.2} MB) in {:.3}s: {:.1} files/sec, {:.2} MB/sec",
+            stats.files(),
+            megabytes,
+            seconds,
+            stats.files() as f64 / seconds,
+            megabytes / seconds,
+        );
+        return Ok(());
+    }
+
+    if let Some(mut allow_list) = AllowList::load(&root_dir)? {
+        for todo_error in &mut todo_errors {
+            if !todo_error.is_tracked()
+                && !todo_error.is_suppressed()
+                && allow_list.allows(&root_dir, todo_error.file_path(), todo_error.row())
+            {
+                todo_error.suppress(format!("listed in {}", ALLOW_LIST_FILE_NAME));
+            }
+        }
+        // Only a full scan sees every listed location. Applied before `--older-than` drops the
+        // newer findings, which entries may still match.
+        if diff_base.is_none() && !opt.since_mtime && only_locations.is_none() {
+            for entry in allow_list.stale() {
+                eprintln!(
+                    "warning: stale {} entry `{}` matches no untracked finding",
+                    ALLOW_LIST_FILE_NAME, entry
+                );
+            }
+        }
+    }
+
+    if let Some(git_ref) = &opt.older_than {
+        let cutoff = blame::ref_time(git_ref)?;
+        let mut blame = blame::Blame::default();
+        todo_errors.retain(|todo_error| {
+            blame
+                .line_time(todo_error.file_path(), todo_error.row())
+                .is_some_and(|time| time <= cutoff)
+        });
+    }
+
+    if !config.third_party_dirs.is_empty() {
+        for todo_error in &mut todo_errors {
+            if paths::is_within(todo_error.file_path(), &root_dir, &config.third_party_dirs) {
+                todo_error.mark_third_party();
+            }
+        }
+    }
+
+    if let Some(test_files) = test_files {
+        for todo_error in &mut todo_errors {
+            if test_files.contains(todo_error.file_path(), &root_dir) {
+                todo_error.downgrade();
             }
+        }
+    }
+
+    if config.style_checks {
+        for todo_error in &mut todo_errors {
+            todo_error.check_style();
+        }
+    }
+
+    let today = json_emitter::utc_date(SystemTime::now());
+    for todo_error in &mut todo_errors {
+        todo_error.relabel(&severity_map, &today);
+    }
+
+    if opt.max_per_file.is_some() {
+        // Group each file's findings, so the same ones are shown on every run and the rest are
+        // summarized right after them.
+        let untracked_first = opt.untracked_first;
+        todo_errors.sort_by(|a, b| {
+            (
+                a.file_path(),
+                untracked_first && a.is_tracked(),
+                a.cell(),
+                a.row(),
+                a.col(),
+            )
+                .cmp(&(
+                    b.file_path(),
+                    untracked_first && b.is_tracked(),
+                    b.cell(),
+                    b.row(),
+                    b.col(),
+                ))
+        });
+    } else if opt.untracked_first {
+        todo_errors.sort_by(|a, b| {
+            (a.is_tracked(), a.file_path(), a.cell(), a.row(), a.col()).cmp(&(
+                b.is_tracked(),
+                b.file_path(),
+                b.cell(),
+                b.row(),
+                b.col(),
+            ))
+        });
+    }
+
+    let (suppressed, todo_errors): (Vec<_>, Vec<_>) = todo_errors
+        .into_iter()
+        .partition(|todo_error| todo_error.is_suppressed());
+
+    if opt.precommit {
+        return passed(precommit(&todo_errors, &stats.file_paths()));
+    }
+
+    if let Some(locations) = &only_locations {
+        return passed(recheck(&todo_errors, locations));
+    }
+
+    if opt.score {
+        println!("{}", debt_score(&config, &todo_errors, &today));
+        return passed(Ok(()));
+    }
+
+    let is_hidden = |todo_error: &TodoError| {
+        todo_error.tracking_id().is_some_and(|tracking_id| {
+            hidden_issues
+                .iter()
+                .any(|pattern| pattern.matches(tracking_id))
         })
-    {
+    };
+
+    let third_party_count = todo_errors
+        .iter()
+        .filter(|todo_error| todo_error.is_third_party())
+        .count();
+
+    for todo_error in todo_errors.into_iter().filter(|todo_error| {
+        if config.only_tracked {
+            todo_error.is_tracked()
+        } else if (!todo_error.is_tracked() && !todo_error.is_third_party())
+            || (config.report_all && (todo_error.is_tracked() || todo_error.is_third_party()))
+        {
+            true
+        } else {
+            false
+        }
+    }) {
+        if is_hidden(&todo_error) {
+            continue;
+        }
+
         issues_found_count += 1;
-        if !todo_error.is_tracked() {
-            has_untracked = true;
+        if let Some(depth) = opt.rollup_depth {
+            *rollup
+                .entry(paths::rollup(todo_error.file_path(), depth))
+                .or_default() += 1;
+        }
+        if todo_error.is_third_party() {
+            third_party_reported_count += 1;
+        } else {
+            match todo_error.severity() {
+                Severity::Error => untracked_count += 1,
+                Severity::Warning => warning_count += 1,
+                Severity::Info => {}
+            }
+        }
+
+        reported.push(todo_error);
+    }
+
+    if config.format == Format::Human && !opt.tui {
+        for shown in limit_per_file(&reported, opt.max_per_file) {
+            match shown {
+                Shown::Finding(todo_error) => stderr.write_error(todo_error)?,
+                Shown::More(file_path, count) => {
+                    eprintln!("... and {} more in {}", count, display_path(file_path))
+                }
+            }
+        }
+    }
+
+    if opt.show_suppressed && config.format == Format::Human {
+        for todo_error in &suppressed {
+            stderr.write_error(todo_error)?;
+        }
+    }
+    stderr.write_folded_help()?;
+
+    let summary = json_emitter::Summary {
+        issues_found: issues_found_count,
+        untracked: untracked_count,
+        warnings: warning_count,
+        tracked: issues_found_count - untracked_count - warning_count - third_party_reported_count,
+        suppressed: suppressed.len(),
+        third_party: third_party_reported_count,
+    };
+
+    if opt.embed_snippet {
+        for todo_error in &mut reported {
+            todo_error.embed_snippet();
+        }
+    }
+
+    let mut scanned = stats.file_paths();
+    let repo_relative;
+    let structured = if opt.repo_relative_paths {
+        let base = paths::repo_root(&root_dir).unwrap_or_else(|| root_dir.clone());
+        debug!("reporting paths relative to {}", base.display());
+        for path in &mut scanned {
+            *path = paths::relative_to(path, &base);
         }
+        repo_relative = reported
+            .iter()
+            .map(|todo_error| {
+                let mut todo_error = todo_error.clone();
+                todo_error.set_file_path(paths::relative_to(todo_error.file_path(), &base));
+                todo_error
+            })
+            .collect::<Vec<_>>();
+        &repo_relative
+    } else {
+        &reported
+    };
+
+    let metadata = json_emitter::Metadata::new(&root_dir, config_hash(&config));
+    let endpoint = match (&opt.emit_socket, &opt.emit_tcp) {
+        (Some(path), _) => Some(socket_emitter::Endpoint::Unix(path.clone())),
+        (None, Some(address)) => Some(socket_emitter::Endpoint::Tcp(address.clone())),
+        (None, None) => None,
+    };
+    let emitter = config.format.emitter(&summary);
+    if opt.tui {
+        browse(&reported)?;
+    } else if let Some(output_dir) = &opt.output_dir {
+        emitter.write_per_file(structured, &scanned, output_dir, opt.empty_reports)?;
+    } else if let Some(endpoint) = &endpoint {
+        socket_emitter::stream(endpoint, structured)?;
+    } else {
+        emitter.finalize(structured, &metadata)?;
+    }
+
+    for also in &opt.also {
+        also.write(structured, &summary, &metadata)?;
+    }
 
-        stderr.write_error(&todo_error)?;
+    if let Some(exec) = &opt.exec {
+        let untracked = reported
+            .iter()
+            .filter(|todo_error| !todo_error.is_tracked())
+            .collect::<Vec<_>>();
+        exec::run_for_each(
+            &exec::CommandTemplate::new(exec)?,
+            &untracked,
+            opt.exec_jobs.unwrap_or_else(num_cpus::get),
+            opt.exec_dry_run,
+        )?;
+    }
+
+    for (directory, count) in &rollup {
+        eprintln!("{:>6}  {}", count, directory);
     }
 
     if issues_found_count > 0 {
         eprintln!("{} issues found.", issues_found_count)
+    } else if opt.success_marker && config.format == Format::Human {
+        println!("report_todo: clean");
+    }
+
+    if !suppressed.is_empty() {
+        eprintln!("{} issues suppressed.", suppressed.len())
+    }
+
+    if third_party_count > 0 {
+        eprintln!("{} issues in third-party directories.", third_party_count)
+    }
+
+    if untracked_count > 0 || config.fail_threshold > 0 {
+        eprintln!(
+            "untracked TODOs: {} (threshold {})",
+            untracked_count, config.fail_threshold
+        );
+    }
+
+    passed(match config.error_on {
+        ErrorOn::Untracked if untracked_count > config.fail_threshold => {
+            Err(PolicyViolation("untracked issues found!".to_owned()).into())
+        }
+        ErrorOn::Any if issues_found_count > config.fail_threshold => {
+            Err(PolicyViolation("issues found!".to_owned()).into())
+        }
+        _ => Ok(()),
+    })
+}
+
+const TUI_UNAVAILABLE: &str = "--tui requires report_todo to be built with `--features tui`";
+
+/// `--tui`.
+#[cfg(feature = "tui")]
+fn browse(findings: &[TodoError]) -> anyhow::Result<()> {
+    report_todo::tui::browse(findings)
+}
+
+/// `--tui`, which is rejected before scanning in a build without the `tui` feature.
+#[cfg(not(feature = "tui"))]
+fn browse(_findings: &[TodoError]) -> anyhow::Result<()> {
+    Err(anyhow!(TUI_UNAVAILABLE))
+}
+
+/// An entry in the human-readable report.
+enum Shown<'a> {
+    Finding(&'a TodoError),
+
+    /// The number of findings in the file left out by `--max-per-file`.
+    More(&'a Path, usize),
+}
+
+/// What the human-readable report shows of `findings`: with `max_per_file`, the first that many
+/// in each file, each file's followed by the number left out. `findings` must be grouped by file.
+fn limit_per_file(findings: &[TodoError], max_per_file: Option<usize>) -> Vec<Shown<'_>> {
+    let max_per_file = match max_per_file {
+        Some(max_per_file) => max_per_file,
+        None => return findings.iter().map(Shown::Finding).collect(),
+    };
+
+    let mut shown = Vec::new();
+    for file in findings.chunk_by(|a, b| a.file_path() == b.file_path()) {
+        shown.extend(file.iter().take(max_per_file).map(Shown::Finding));
+        if file.len() > max_per_file {
+            shown.push(Shown::More(file[0].file_path(), file.len() - max_per_file));
+        }
+    }
+    shown
+}
+
+/// Prints the problems with `config`, failing if there are any.
+fn config_check(config: &Config) -> anyhow::Result<()> {
+    let problems = config_problems(config);
+    if problems.is_empty() {
+        println!("OK");
+        return Ok(());
+    }
+
+    for problem in &problems {
+        eprintln!("error: {}", problem);
+    }
+    Err(PolicyViolation(format!("{} problems found in the config", problems.len())).into())
+}
+
+fn config_problems(config: &Config) -> Vec<String> {
+    let regexes = match build_regexes(config) {
+        Ok(regexes) => regexes,
+        Err(error) => return vec![format!("{:#}", error)],
+    };
+
+    let mut problems = Vec::new();
+    if let Err(error) = SeverityMap::parse(&config.severity_map) {
+        problems.push(format!("invalid --severity-map: {:#}", error));
+    }
+    if let Err(error) = TestFiles::new(&config.test_patterns) {
+        problems.push(format!("invalid --test-patterns: {:#}", error));
+    }
+    // Checks the patterns as the scan uses them, which `build_regexes` keeps in order.
+    for (pattern, issue_pattern) in config.match_issue.iter().zip(&regexes.match_issues) {
+        let match_issue = &issue_pattern.regex;
+        if match_issue.captures_len() == 1 {
+            problems.push(format!(
+                "--match-issue '{}' has no capture group for the tracking id",
+                pattern
+            ));
+        } else if match_issue.capture_names().flatten().count() > 0
+            && !match_issue
+                .capture_names()
+                .any(|name| name == Some(config.id_group.as_str()))
+        {
+            problems.push(format!(
+                "--match-issue '{}' names its groups, but none `{}`; the first group is used as \
+                the tracking id",
+                pattern, config.id_group
+            ));
+        }
+
+        if let Some(issue_link_format) = &issue_pattern.link_format {
+            for group in link_references(issue_link_format) {
+                let defined = match group.parse::<usize>() {
+                    Ok(index) => index < match_issue.captures_len(),
+                    Err(_) => match_issue.capture_names().any(|name| name == Some(group)),
+                };
+                if !defined {
+                    problems.push(format!(
+                        "--issue-link-format refers to `{}`, which --match-issue '{}' doesn't \
+                        capture",
+                        group, pattern
+                    ));
+                }
+            }
+        }
+    }
+
+    problems
+}
+
+/// The `--issue-link-format` for the `index`th `--match-issue`: the one in the same position, or
+/// the only one.
+fn issue_link_format(config: &Config, index: usize) -> Option<&str> {
+    match config.issue_link_format.as_slice() {
+        [only] => Some(only),
+        formats => formats.get(index).map(String::as_str),
+    }
+}
+
+/// Accepts a single string where a list of them is expected, as options that were once single
+/// values are still written that way in config files.
+fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// The groups `$name`, `${name}` or `$1` referred to by `format`, as interpolated by
+/// `regex::Captures::expand`.
+fn link_references(format: &str) -> Vec<&str> {
+    let mut references = Vec::new();
+    let mut rest = format;
+    while let Some(dollar) = rest.find('$') {
+        rest = &rest[dollar + 1..];
+        if let Some(escaped) = rest.strip_prefix('$') {
+            rest = escaped;
+        } else if let Some(braced) = rest.strip_prefix('{') {
+            if let Some(end) = braced.find('}') {
+                references.push(&braced[..end]);
+                rest = &braced[end + 1..];
+            }
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if end > 0 {
+                references.push(&rest[..end]);
+            }
+            rest = &rest[end..];
+        }
+    }
+    references
+}
+
+/// The matches of the subcommand that was run, e.g. `config dump`, which hold its options.
+fn innermost_subcommand<'a>(matches: &'a ArgMatches<'a>) -> &'a ArgMatches<'a> {
+    match matches.subcommand() {
+        (_, Some(subcommand)) => innermost_subcommand(subcommand),
+        _ => matches,
+    }
+}
+
+/// The nearest `report_todo.toml` in `dir` or one of its parents.
+fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    let dir = dir.canonicalize().ok()?;
+    dir.ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Applies the nearest `report_todo.toml` at or above `root` to `config`, as parsed from the
+/// options in `matches`. Options passed on the command line take precedence over the file, which
+/// takes precedence over the defaults.
+fn load_config(config: Config, root: &Path, matches: &ArgMatches) -> anyhow::Result<Config> {
+    let path = match find_config_file(root) {
+        Some(path) => path,
+        None => return Ok(config),
+    };
+    debug!("loading {}", path.display());
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let file =
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))?;
+    merge_config(config, file, matches).with_context(|| format!("invalid {}", path.display()))
+}
+
+/// Overlays the fields set in `file` on the defaults, and then the options passed in `matches`.
+fn merge_config(config: Config, file: toml::Value, matches: &ArgMatches) -> anyhow::Result<Config> {
+    // Merged by field name, which is each option's name in `matches` in kebab case.
+    let mut merged = serde_json::to_value(Config::from_iter_safe(&["report_todo"])?)?;
+    let fields = merged.as_object_mut().unwrap();
+    match serde_json::to_value(file)? {
+        serde_json::Value::Object(file) => fields.extend(file),
+        _ => unreachable!("a TOML document is a table"),
+    }
+    if let serde_json::Value::Object(passed) = serde_json::to_value(&config)? {
+        fields.extend(
+            passed
+                .into_iter()
+                .filter(|(name, _)| matches.occurrences_of(name.replace('_', "-")) > 0),
+        );
+    }
+    Ok(serde_json::from_value(merged)?)
+}
+
+/// Writes `starter_config` to `report_todo.toml`, refusing to overwrite it unless `force`.
+fn config_init(force: bool) -> anyhow::Result<()> {
+    let path = Path::new(CONFIG_FILE_NAME);
+    if path.exists() && !force {
+        return Err(anyhow!(
+            "{} already exists; pass `--force` to overwrite it",
+            CONFIG_FILE_NAME
+        ));
+    }
+
+    std::fs::write(path, starter_config()?)
+        .with_context(|| format!("failed to write {}", CONFIG_FILE_NAME))?;
+    eprintln!("wrote {}", CONFIG_FILE_NAME);
+    Ok(())
+}
+
+/// The default configuration as commented TOML, with a placeholder issue link and some example
+/// keywords filled in.
+fn starter_config() -> anyhow::Result<String> {
+    let mut config = Config::from_iter_safe(&["report_todo"])?;
+    config.issue_link_format =
+        vec!["https://github.com/OWNER/REPO/issues/${issue_number}".to_owned()];
+    config.forbidden_keywords = vec!["todo".to_owned(), "fixme".to_owned()];
+    config.warn_keywords = vec!["hack".to_owned()];
+
+    let mut starter = String::from("# report_todo configuration.\n");
+    for line in toml::to_string(&config)?.lines() {
+        let key = line.split(" = ").next().unwrap_or("");
+        if let Some((_, comment)) = CONFIG_COMMENTS.iter().find(|(name, _)| *name == key) {
+            starter.push_str(&format!("\n# {}\n", comment));
+        }
+        starter.push_str(line);
+        starter.push('\n');
+    }
+    Ok(starter)
+}
+
+/// Prints what findings with the rule id `rule` are, and what to do about them.
+fn explain(rule: &str) -> anyhow::Result<()> {
+    let mut parts = rule.split('/');
+    let (keyword, tracked) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some("report-todo"), Some(keyword), Some("untracked"), None) => (keyword, false),
+        (Some("report-todo"), Some(keyword), Some("tracked"), None) => (keyword, true),
+        _ => {
+            return Err(anyhow!(
+                "unknown rule `{}`; rules look like `report-todo/<keyword>/untracked` or \
+                `report-todo/<keyword>/tracked`",
+                rule
+            ))
+        }
+    };
+
+    let keyword = keyword.to_uppercase();
+    if tracked {
+        println!(
+            "{}: a `{}` that references an issue, e.g. `{}(#1): ...`.",
+            rule, keyword, keyword
+        );
+        println!();
+        println!("Tracked issues are informational: they're only reported with `--all` or");
+        println!("`--only-tracked`, and never fail the run.");
+    } else {
+        println!("{}: a `{}` without an issue reference.", rule, keyword);
+        println!();
+        println!("Untracked issues fail the run (beyond `--fail-threshold`), unless the keyword");
+        println!("is one of `--warn-keywords`. Create a work item and reference it, e.g.");
+        println!("`{}(#1): ...`, or suppress the finding with a", keyword);
+        println!("`report-todo:allow(reason)` marker on the same line, or alone on the");
+        println!("line before.");
+    }
+
+    Ok(())
+}
+
+/// Renders sample findings in `format` and checks the result has the layout consumers expect.
+fn selftest(format: Format) -> anyhow::Result<()> {
+    let findings = [TodoError::sample(true), TodoError::sample(false)];
+    let summary = json_emitter::Summary::tally(&findings);
+    let metadata = json_emitter::Metadata::new(Path::new("."), "0".repeat(16));
+
+    let emitter = format.emitter(&summary);
+    let mut output = Vec::new();
+    emitter.write_to(&mut output, &findings, &metadata)?;
+    let output = String::from_utf8(output).context("output is not UTF-8")?;
+    emitter
+        .validate(&output, &findings)
+        .context("selftest failed")?;
+
+    println!("OK");
+    Ok(())
+}
+
+/// Identifies the effective `config` in report metadata.
+fn config_hash(config: &Config) -> String {
+    // Serializing `Config` to JSON can't fail, as it has no maps with non-string keys.
+    todo_error::stable_hash(&[&serde_json::to_string(config).unwrap()])
+}
+
+/// Compiles the patterns in `config`. Errors name the flag and pattern that failed to compile.
+fn build_regexes(config: &Config) -> anyhow::Result<Regexes> {
+    fn keyword_regexes<'a>(
+        flag: &str,
+        keywords: impl Iterator<Item = &'a String>,
+        fuzzy: bool,
+    ) -> anyhow::Result<Vec<Regex>> {
+        // The keyword itself is captured, so that variants are reported as the keyword.
+        let suffix = if fuzzy { r"(?:s?\b|\d+\b|_)" } else { r"\b" };
+        keywords
+            .map(|keyword| {
+                regex::RegexBuilder::new(&format!(r"\b(?P<keyword>{}){}", keyword, suffix))
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("invalid {} pattern '{}'", flag, keyword))
+            })
+            .collect()
+    }
+    let is_warning = |keyword: &&String| {
+        config
+            .warn_keywords
+            .iter()
+            .any(|warning| warning.eq_ignore_ascii_case(keyword))
+    };
+
+    let mut bad_keywords = keyword_regexes(
+        "--forbid",
+        config
+            .forbidden_keywords
+            .iter()
+            .filter(|keyword| !is_warning(keyword)),
+        config.fuzzy,
+    )?;
+    bad_keywords.extend(keyword_regexes(
+        "--error-keywords",
+        config
+            .error_keywords
+            .iter()
+            .filter(|keyword| !is_warning(keyword)),
+        config.fuzzy,
+    )?);
+
+    let rust_macros = config
+        .rust_macros
+        .iter()
+        .map(|rust_macro| rust_macro.parse::<RustMacro>())
+        .collect::<anyhow::Result<Vec<_>>>()
+        .context("invalid --rust-macros")?;
+    let rust_macros = if rust_macros.is_empty() {
+        None
+    } else {
+        Some(RustMacros::new(rust_macros).context("invalid --rust-macros")?)
+    };
+
+    let languages = config
+        .languages
+        .iter()
+        .map(|language| {
+            if language.extensions.is_empty() {
+                Err(anyhow!("invalid [[language]]: no `extensions`"))
+            } else if language.line_comment.is_none() && language.block_comment.is_none() {
+                Err(anyhow!(
+                    "invalid [[language]] for `{}`: no `line_comment` or `block_comment`",
+                    language.extensions[0]
+                ))
+            } else {
+                Ok(Arc::new(language.clone()))
+            }
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if config.issue_link_format.len() > 1
+        && config.issue_link_format.len() != config.match_issue.len()
+    {
+        return Err(anyhow!(
+            "expected one --issue-link-format per --match-issue, or one for all of them; got {} \
+            for {} patterns",
+            config.issue_link_format.len(),
+            config.match_issue.len()
+        ));
+    }
+    let match_issues = config
+        .match_issue
+        .iter()
+        .enumerate()
+        .map(|(index, match_issue)| {
+            Ok(IssuePattern {
+                regex: regex::RegexBuilder::new(&format!(r"\b{}", match_issue))
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("invalid --match-issue pattern '{}'", match_issue))?,
+                link_format: issue_link_format(config, index).map(str::to_owned),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // The default `--match-issue` can only match where `todo` appears.
+    let prefilter = if config.match_issue == [DEFAULT_MATCH_ISSUE] {
+        LiteralPrefilter::new(
+            config
+                .forbidden_keywords
+                .iter()
+                .chain(&config.error_keywords)
+                .chain(&config.warn_keywords)
+                .map(String::as_str)
+                .chain(rust_macros.iter().flat_map(RustMacros::reported_names))
+                .chain(Some("todo")),
+        )
+    } else {
+        None
+    };
+
+    Ok(Regexes {
+        match_issues,
+        match_issue_url: config
+            .match_issue_url
+            .as_ref()
+            .map(|match_issue_url| {
+                regex::RegexBuilder::new(match_issue_url)
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| {
+                        format!("invalid --match-issue-url pattern '{}'", match_issue_url)
+                    })
+            })
+            .transpose()?,
+        bad_keywords,
+        warning_keywords: keyword_regexes(
+            "--warn-keywords",
+            config.warn_keywords.iter(),
+            config.fuzzy,
+        )?,
+        allow_marker: regex::RegexBuilder::new(ALLOW_MARKER)
+            .case_insensitive(true)
+            .build()?,
+        match_wrapped: config.match_wrapped,
+        first_match_only: config.first_match_only,
+        groups: CaptureGroups {
+            id: config.id_group.clone(),
+            assignee: config.assignee_group.clone(),
+            due: config.due_group.clone(),
+            project: config.project_group.clone(),
+        },
+        rust_macros,
+        languages,
+        prefilter,
+    })
+}
+
+/// Adds findings from the whole of each file in the diff to `diff_errors`, which are the findings
+/// on the diff's added lines. Findings the diff already reported are skipped.
+fn scan_touched_files(
+    diff_errors: &mut Vec<TodoError>,
+    regexes: &Regexes,
+    diff_stats: &ScanStats,
+    skip: &SkipFiles,
+) -> anyhow::Result<()> {
+    for todo_error in diff_errors
+        .iter_mut()
+        .filter(|todo_error| !todo_error.is_removed())
+    {
+        todo_error.set_origin(Origin::DiffAdded);
+    }
+
+    // Deleted files are in the diff, but there's nothing left to scan.
+    let touched = diff_stats
+        .file_paths()
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    if touched.is_empty() {
+        return Ok(());
+    }
+
+    let already_reported = diff_errors
+        .iter()
+        .filter(|todo_error| !todo_error.is_removed())
+        .map(|todo_error| {
+            (
+                todo_error.file_path().to_owned(),
+                todo_error.cell(),
+                todo_error.row(),
+                todo_error.col(),
+            )
+        })
+        .collect::<HashSet<_>>();
+
+    let checker = SourceTreeSimpleChecker {
+        roots: touched,
+        modified_since: None,
+        max_depth: None,
+        stream_above: None,
+        threads: None,
+        skip: skip.clone(),
+    };
+    for mut todo_error in checker.process_spans(regexes, &ScanStats::default())? {
+        let location = (
+            todo_error.file_path().to_owned(),
+            todo_error.cell(),
+            todo_error.row(),
+            todo_error.col(),
+        );
+        if !already_reported.contains(&location) {
+            todo_error.set_origin(Origin::FileExisting);
+            todo_error.downgrade();
+            diff_errors.push(todo_error);
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints `PASS <file>` or `FAIL <file> (<n> untracked)` for each scanned file, failing if any file
+/// has untracked issues.
+fn precommit(todo_errors: &[TodoError], scanned: &[PathBuf]) -> anyhow::Result<()> {
+    let mut untracked_by_file = BTreeMap::<&Path, usize>::new();
+    for path in scanned {
+        untracked_by_file.entry(path).or_default();
+    }
+    for todo_error in todo_errors {
+        if todo_error.severity() == Severity::Error {
+            *untracked_by_file.entry(todo_error.file_path()).or_default() += 1;
+        }
+    }
+
+    let mut failed = 0;
+    for (path, untracked) in untracked_by_file {
+        if untracked == 0 {
+            println!("PASS {}", display_path(path));
+        } else {
+            failed += 1;
+            println!("FAIL {} ({} untracked)", display_path(path), untracked);
+        }
+    }
+
+    if failed > 0 {
+        return Err(PolicyViolation(format!("untracked issues found in {} files!", failed)).into());
+    }
+
+    Ok(())
+}
+
+/// Prints `UNTRACKED <path>:<line>` or `RESOLVED <path>:<line>` for each location, failing if any
+/// still has an untracked issue.
+fn recheck(todo_errors: &[TodoError], locations: &[Location]) -> anyhow::Result<()> {
+    let untracked = todo_errors
+        .iter()
+        .filter(|todo_error| todo_error.severity() == Severity::Error)
+        .map(|todo_error| (todo_error.file_path(), todo_error.row()))
+        .collect::<HashSet<_>>();
+
+    let mut remaining = 0;
+    for location in locations.iter().collect::<BTreeSet<_>>() {
+        if untracked.contains(&(location.path.as_path(), location.line)) {
+            remaining += 1;
+            println!(
+                "UNTRACKED {}:{}",
+                display_path(&location.path),
+                location.line
+            );
+        } else {
+            println!(
+                "RESOLVED {}:{}",
+                display_path(&location.path),
+                location.line
+            );
+        }
+    }
+
+    if remaining > 0 {
+        return Err(PolicyViolation(format!(
+            "untracked issues remain at {} locations!",
+            remaining
+        ))
+        .into());
     }
 
-    if has_untracked {
-        return Err(anyhow!("untracked issues found!"));
+    Ok(())
+}
+
+/// Where `--since-mtime` records when it last passed on `root_dir`: in the git directory of the
+/// repository containing it, or outside one, in the user's cache directory, so that the scanned
+/// tree isn't written to. Each root has its own record.
+fn last_run_path(root_dir: &Path) -> anyhow::Result<PathBuf> {
+    let root = root_dir.canonicalize()?;
+    let file_name = format!(
+        "mtime-{}",
+        todo_error::stable_hash(&[&root.to_string_lossy()])
+    );
+    let dir = match paths::git_path(&root, "report_todo") {
+        Some(dir) => dir,
+        None => cache_dir()
+            .ok_or_else(|| anyhow!("--since-mtime outside a git repository needs $HOME"))?
+            .join("report_todo"),
+    };
+    Ok(dir.join(file_name))
+}
+
+/// `$XDG_CACHE_HOME`, `~/.cache` or, on Windows, `%LOCALAPPDATA%`.
+fn cache_dir() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+    var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| Path::new(&home).join(".cache")))
+        .or_else(|| var("LOCALAPPDATA").map(PathBuf::from))
+}
+
+fn read_last_run(path: &Path) -> anyhow::Result<Option<SystemTime>> {
+    if !path.exists() {
+        return Ok(None);
     }
 
+    let seconds: u64 = std::fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid timestamp in {}", path.display()))?;
+    Ok(Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)))
+}
+
+fn write_last_run(path: &Path, time: SystemTime) -> anyhow::Result<()> {
+    // Truncates to whole seconds, so files modified in the same second are scanned again.
+    let seconds = time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, format!("{}\n", seconds))
+        .with_context(|| format!("couldn't record the run in {}", path.display()))?;
     Ok(())
 }
+
+/// The `--score` of `todo_errors`, with overdue meaning due before `today`, a `YYYY-MM-DD` date.
+fn debt_score(config: &Config, todo_errors: &[TodoError], today: &str) -> f64 {
+    todo_errors
+        .iter()
+        .map(|todo_error| {
+            if !todo_error.is_tracked() {
+                config.untracked_weight
+            } else if todo_error.is_overdue(today) {
+                config.overdue_weight
+            } else {
+                config.tracked_weight
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(args: &[&str]) -> Config {
+        Config::from_iter_safe(std::iter::once("report_todo").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn config_file_precedence() {
+        let args = ["report_todo", "--forbid", "hack", "--format", "json"];
+        let matches = Config::clap().get_matches_from(args.iter().copied());
+        let file = toml::from_str(
+            r#"
+            forbidden_keywords = ["fixme"]
+            warn_keywords = ["xxx"]
+            fail_threshold = 3
+            error_on = "any"
+            "#,
+        )
+        .unwrap();
+        let config = merge_config(Config::from_clap(&matches), file, &matches).unwrap();
+
+        assert_eq!(config.forbidden_keywords, vec!["hack"]);
+        assert_eq!(config.format, Format::Json);
+        assert_eq!(config.warn_keywords, vec!["xxx"]);
+        assert_eq!(config.fail_threshold, 3);
+        assert_eq!(config.error_on, ErrorOn::Any);
+        assert_eq!(config.match_issue, vec![DEFAULT_MATCH_ISSUE]);
+
+        let file = toml::from_str("fail_threshold = \"none\"").unwrap();
+        assert!(merge_config(Config::from_clap(&matches), file, &matches).is_err());
+    }
+
+    #[test]
+    fn config_file_is_found_in_parents() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(
+            find_config_file(&manifest_dir.join("src/checkers")),
+            Some(manifest_dir.canonicalize().unwrap().join(CONFIG_FILE_NAME))
+        );
+    }
+
+    #[test]
+    fn starter_config_round_trips() {
+        let starter = starter_config().unwrap();
+        let config: Config = toml::from_str(&starter).unwrap();
+
+        assert!(starter.contains("\n# Keywords reported as errors"));
+        assert_eq!(config.forbidden_keywords, vec!["todo", "fixme"]);
+        assert_eq!(config.match_issue, vec![DEFAULT_MATCH_ISSUE]);
+        assert!(build_regexes(&config).is_ok());
+    }
+
+    #[test]
+    fn parse_also_output() {
+        let also = "json:out/findings.json".parse::<AlsoOutput>().unwrap();
+        assert_eq!(also.format, Format::Json);
+        assert_eq!(also.path, Path::new("out/findings.json"));
+
+        let also = r"step-summary:C:\summary.md".parse::<AlsoOutput>().unwrap();
+        assert_eq!(also.path, Path::new(r"C:\summary.md"));
+
+        let also = "human:out.txt".parse::<AlsoOutput>().unwrap();
+        assert_eq!(also.format, Format::Human);
+
+        assert!("json".parse::<AlsoOutput>().is_err());
+        assert!("json:".parse::<AlsoOutput>().is_err());
+        assert!("xml:out.xml".parse::<AlsoOutput>().is_err());
+    }
+
+    #[test]
+    fn fuzzy_keyword_variants() {
+        let keywords = |args: &[&str], line: &str| {
+            TodoError::from_line(
+                &build_regexes(&config(args)).unwrap(),
+                Path::new("a.rs"),
+                line,
+                1,
+            )
+            .iter()
+            .map(|todo_error| todo_error.keyword().to_owned())
+            .collect::<Vec<_>>()
+        };
+
+        for line in [
+            "// TODO",
+            "// TODO: a",
+            "// TODO - a",
+            "// @TODO a",
+            "// (todo) a",
+            "// TODOs a",
+            "// TODOS: a",
+            "// TODO2: a",
+            "// TODO_later",
+        ] {
+            assert_eq!(keywords(&["--fuzzy"], line), vec!["todo"], "{}", line);
+        }
+        for line in ["// TODOs a", "// TODO2: a", "// TODO_later"] {
+            assert!(keywords(&[], line).is_empty(), "{}", line);
+        }
+        for line in [
+            "// todoist",
+            "// mastodon",
+            "// TODOSX",
+            "// TODO2b",
+            "// XTODO",
+        ] {
+            assert!(keywords(&["--fuzzy"], line).is_empty(), "{}", line);
+        }
+
+        let todo_errors = TodoError::from_line(
+            &build_regexes(&config(&["--fuzzy"])).unwrap(),
+            Path::new("a.rs"),
+            "// TODOs here",
+            1,
+        );
+        assert_eq!(todo_errors[0].message(), "TODO found without issue number");
+    }
+
+    #[test]
+    fn selftest_passes() {
+        for format in [
+            Format::Human,
+            Format::Json,
+            Format::JsonPretty,
+            Format::Ndjson,
+            Format::Github,
+            Format::Sarif,
+            Format::Checkstyle,
+            Format::StepSummary,
+        ] {
+            selftest(format).unwrap();
+        }
+        assert!(json_emitter::validate(r#"{"version": 1, "findings": []}"#, 0).is_err());
+    }
+
+    #[test]
+    fn check_config() {
+        assert!(config_problems(&toml::from_str(&starter_config().unwrap()).unwrap()).is_empty());
+        assert!(config_problems(&config(&[])).is_empty());
+
+        let problems = config_problems(&config(&["--match-issue", "todo:"]));
+        assert_eq!(
+            problems,
+            vec!["--match-issue 'todo:' has no capture group for the tracking id"]
+        );
+
+        let problems = config_problems(&config(&[
+            "--issue-link-format",
+            "https://example.com/${issue} $1 $2",
+        ]));
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("`issue`"));
+        assert!(problems[1].contains("`2`"));
+
+        assert_eq!(config_problems(&config(&["--forbid", "fix[me"])).len(), 1);
+        assert!(config_problems(&config(&["--severity-map", "overdue=error"])).is_empty());
+        assert_eq!(
+            config_problems(&config(&["--severity-map", "late=error"])).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn several_issue_patterns() {
+        let regexes = build_regexes(&config(&[
+            "--match-issue",
+            r"todo\(#(?P<issue_number>\d+)\)",
+            "--match-issue",
+            r"todo\((?P<issue_number>[A-Z]+-\d+)\)",
+            "--issue-link-format",
+            "https://github.com/org/repo/issues/${issue_number}",
+            "--issue-link-format",
+            "https://jira.example.com/browse/${issue_number}",
+        ]))
+        .unwrap();
+
+        let links = ["// TODO(#123): a", "// TODO(JIRA-456): b"]
+            .iter()
+            .flat_map(|line| TodoError::from_line(&regexes, Path::new("a.rs"), line, 1))
+            .map(|todo_error| todo_error.link().map(str::to_owned))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            links,
+            vec![
+                Some("https://github.com/org/repo/issues/123".to_owned()),
+                Some("https://jira.example.com/browse/JIRA-456".to_owned()),
+            ]
+        );
+
+        assert!(build_regexes(&config(&[
+            "--match-issue",
+            "a",
+            "--match-issue",
+            "b",
+            "--match-issue",
+            "c",
+            "--issue-link-format",
+            "x",
+            "--issue-link-format",
+            "y",
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn error_on_levels() {
+        assert_eq!(config(&[]).error_on, ErrorOn::Untracked);
+        assert_eq!(config(&["--error-on", "any"]).error_on, ErrorOn::Any);
+        assert_eq!(config(&["--error-on", "never"]).error_on, ErrorOn::Never);
+        assert!(Config::from_iter_safe(&["report_todo", "--error-on", "tracked"]).is_err());
+    }
+
+    #[test]
+    fn auto_color() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| OsString::from(value))
+            }
+        };
+
+        assert_eq!(
+            color_choice(ColorChoice::Auto, true, env(&[])),
+            ColorChoice::Always
+        );
+        assert_eq!(
+            color_choice(ColorChoice::Auto, false, env(&[])),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            color_choice(ColorChoice::Auto, true, env(&[("NO_COLOR", "1")])),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            color_choice(ColorChoice::Auto, true, env(&[("NO_COLOR", "")])),
+            ColorChoice::Always
+        );
+        assert_eq!(
+            color_choice(ColorChoice::Auto, true, env(&[("TERM", "dumb")])),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            color_choice(ColorChoice::Always, false, env(&[("NO_COLOR", "1")])),
+            ColorChoice::Always
+        );
+        assert_eq!(parse_color_choice("never").unwrap(), ColorChoice::Never);
+    }
+
+    #[test]
+    fn link_format_references() {
+        assert_eq!(
+            link_references("https://x/${issue_number}/$1/$$/$name.html/${a"),
+            vec!["issue_number", "1", "name"]
+        );
+    }
+
+    #[test]
+    fn invalid_pattern_is_named() {
+        let error =
+            build_regexes(&config(&["--forbid", "todo", "--forbid", "fix[me"])).unwrap_err();
+        assert!(format!("{:#}", error).starts_with("invalid --forbid pattern 'fix[me': "));
+
+        let error = build_regexes(&config(&["--match-issue", "todo(#"])).unwrap_err();
+        assert!(format!("{:#}", error).starts_with("invalid --match-issue pattern 'todo(#': "));
+    }
+
+    #[test]
+    fn debt_score_weights() {
+        let config = config(&[
+            "--match-issue",
+            r"todo\(#(?P<issue_number>\d+)(?:, due (?P<due>[^)]+))?\):",
+        ]);
+        let regexes = build_regexes(&config).unwrap();
+        let todo_errors = [
+            "// TODO: untracked",
+            "// TODO(#1): tracked",
+            "// TODO(#2, due 2024-01-31): overdue",
+            "// TODO(#3, due 2024-02-01): due today",
+            "// TODO(#4, due end of sprint): not a date",
+        ]
+        .iter()
+        .flat_map(|line| TodoError::from_line(&regexes, Path::new("a.rs"), line, 1))
+        .collect::<Vec<_>>();
+
+        // 5 + 1 + 10 + 1 + 1
+        assert_eq!(debt_score(&config, &todo_errors, "2024-02-01"), 18.0);
+    }
+
+    #[test]
+    fn limited_per_file() {
+        let finding = |path: &str, tracked: bool| {
+            let mut todo_error = TodoError::sample(tracked);
+            todo_error.set_file_path(PathBuf::from(path));
+            todo_error
+        };
+        let findings = [
+            finding("a.rs", true),
+            finding("a.rs", false),
+            finding("a.rs", false),
+            finding("b.rs", false),
+        ];
+
+        let shown = |max_per_file| {
+            limit_per_file(&findings, max_per_file)
+                .into_iter()
+                .map(|shown| match shown {
+                    Shown::Finding(todo_error) => format!(
+                        "{}:{}",
+                        display_path(todo_error.file_path()),
+                        todo_error.row()
+                    ),
+                    Shown::More(file_path, count) => {
+                        format!("{} more in {}", count, display_path(file_path))
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            shown(Some(2)),
+            ["a.rs:1", "a.rs:2", "1 more in a.rs", "b.rs:2"]
+        );
+        assert_eq!(shown(None), ["a.rs:1", "a.rs:2", "a.rs:2", "b.rs:2"]);
+    }
+
+    #[test]
+    fn last_run_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = temp_dir.path();
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        duct::cmd!("git", "init", "--quiet")
+            .dir(repo)
+            .run()
+            .unwrap();
+
+        let git_dir = repo.canonicalize().unwrap().join(".git");
+        let path = last_run_path(&repo.join("src")).unwrap();
+        let other_root = last_run_path(repo).unwrap();
+        let before = read_last_run(&path).unwrap();
+        let time = SystemTime::UNIX_EPOCH + Duration::from_millis(1_600_000_000_500);
+        write_last_run(&path, time).unwrap();
+        let after = read_last_run(&path).unwrap();
+        let state_dir = path.parent().unwrap().canonicalize().unwrap();
+        let status = duct::cmd!("git", "status", "--porcelain", "--untracked-files=all")
+            .dir(repo)
+            .read()
+            .unwrap();
+
+        assert_eq!(state_dir, git_dir.join("report_todo"));
+        assert_ne!(path, other_root);
+        assert_eq!(before, None);
+        assert_eq!(
+            after,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+        );
+        // Nothing is written to the working tree.
+        assert_eq!(status, "");
+    }
+}
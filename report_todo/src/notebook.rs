@@ -0,0 +1,79 @@
+//! Extracting code from Jupyter notebooks, so findings aren't buried in the notebook's JSON.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Notebook {
+    cells: Vec<Cell>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Cell {
+    cell_type: String,
+    #[serde(default)]
+    source: Option<Source>,
+}
+
+/// Cell source is usually split into lines, each keeping its trailing newline, but may be one
+/// string.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Source {
+    Lines(Vec<String>),
+    Text(String),
+}
+
+/// Whether `path` is a notebook, by its extension.
+pub fn is_notebook(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ipynb"))
+}
+
+/// The source of each code cell in `contents`, with its 1-based index among all cells. Only the
+/// nbformat 4 layout is understood.
+pub fn code_cells(contents: &str) -> serde_json::Result<Vec<(usize, String)>> {
+    let notebook = serde_json::from_str::<Notebook>(contents)?;
+    Ok(notebook
+        .cells
+        .into_iter()
+        .enumerate()
+        .filter(|(_, cell)| cell.cell_type == "code")
+        .map(|(index, cell)| {
+            let source = match cell.source {
+                Some(Source::Lines(lines)) => lines.concat(),
+                Some(Source::Text(text)) => text,
+                None => String::new(),
+            };
+            (index + 1, source)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_code_cells() {
+        let contents = r##"{
+            "cells": [
+                {"cell_type": "markdown", "source": ["# TODO: not code\n"]},
+                {"cell_type": "code", "source": ["import pandas\n", "# TODO: tidy\n"]},
+                {"cell_type": "code", "source": "x = 1"}
+            ],
+            "nbformat": 4
+        }"##;
+        assert_eq!(
+            code_cells(contents).unwrap(),
+            vec![
+                (2, "import pandas\n# TODO: tidy\n".to_owned()),
+                (3, "x = 1".to_owned()),
+            ]
+        );
+        assert!(code_cells("not json").is_err());
+        assert!(is_notebook(Path::new("./analysis.IPYNB")));
+        assert!(!is_notebook(Path::new("./analysis.py")));
+    }
+}
@@ -0,0 +1,374 @@
+//! Consistent rendering of paths across platforms.
+
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use log::debug;
+use serde::Serializer;
+
+/// Renders `path` with `/` separators, so findings read the same on every platform.
+pub fn display_path(path: &Path) -> String {
+    normalize(&path.to_string_lossy(), MAIN_SEPARATOR)
+}
+
+/// For `#[serde(serialize_with)]`, serializes a path as [`display_path`] renders it.
+pub fn serialize_path<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&display_path(path))
+}
+
+/// Converts a `/`-separated path relative to the repository root, as printed by `git`, to a native
+/// path under `root`, the repository root as seen from the current directory, e.g. `.` or `..`.
+pub fn from_repo_relative(root: &Path, path: &str) -> PathBuf {
+    path.split('/')
+        .filter(|component| !component.is_empty())
+        .fold(root.to_owned(), |path, component| path.join(component))
+}
+
+/// `root`, a directory containing the current directory, as a relative path made of `..`s, or `.`
+/// for the current directory itself. Falls back to `root` as it is if it doesn't contain the
+/// current directory.
+pub fn up_to(root: &Path) -> PathBuf {
+    let depth = std::env::current_dir()
+        .and_then(|current_dir| current_dir.canonicalize())
+        .ok()
+        .and_then(|current_dir| {
+            let root = root.canonicalize().ok()?;
+            Some(current_dir.strip_prefix(root).ok()?.components().count())
+        });
+    match depth {
+        Some(0) => PathBuf::from("."),
+        Some(depth) => (0..depth).map(|_| "..").collect(),
+        None => root.to_owned(),
+    }
+}
+
+/// The root of the git working tree containing `dir`, or `None` outside one.
+pub fn repo_root(dir: &Path) -> Option<PathBuf> {
+    debug!(
+        "Running `git rev-parse --show-toplevel` in {}",
+        dir.display()
+    );
+    duct::cmd!("git", "rev-parse", "--show-toplevel")
+        .dir(dir)
+        .stderr_null()
+        .read()
+        .ok()
+        .map(|root| PathBuf::from(root.trim()))
+}
+
+/// Where git keeps `name` for the repository containing `dir`, e.g. `dir/.git/name`, or `None`
+/// outside one. See `git rev-parse --git-path`.
+pub fn git_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    debug!("Running `git rev-parse --git-path` in {}", dir.display());
+    duct::cmd!("git", "rev-parse", "--git-path", name)
+        .dir(dir)
+        .stderr_null()
+        .read()
+        .ok()
+        // Relative to `dir`, unless it's elsewhere, e.g. in a worktree's common directory.
+        .map(|path| dir.join(path.trim()))
+}
+
+/// `path` (relative to the current directory, or absolute) relative to `base`. Paths outside
+/// `base` are left as they are.
+pub fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    // Canonicalize both, so that `..` and symlinks (e.g. macOS's `/tmp`) don't prevent a match.
+    // Files deleted since they were scanned can't be, so fall back to joining the current
+    // directory.
+    let absolute = |path: &Path| {
+        std::fs::canonicalize(path).unwrap_or_else(|_| {
+            std::env::current_dir()
+                .map(|current_dir| current_dir.join(path))
+                .unwrap_or_else(|_| path.to_owned())
+        })
+    };
+    absolute(path)
+        .strip_prefix(absolute(base))
+        .map_or_else(|_| path.to_owned(), Path::to_owned)
+}
+
+/// The deepest directory containing all of `roots` (at least one), which may be files, for the
+/// state shared by one scan of them: the config, `.todo-allow` and `--since-mtime` record.
+///
+/// Lexical where possible, so that it's a prefix of the paths found under the roots. Roots that
+/// only meet above a `..`, or mix absolute and relative paths, get the canonical directory.
+pub fn common_dir(roots: &[PathBuf]) -> PathBuf {
+    let dirs = roots
+        .iter()
+        .map(|root| match root.parent() {
+            _ if root.is_dir() => root.clone(),
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+            _ => PathBuf::from("."),
+        })
+        .collect::<Vec<_>>();
+    let common = |dirs: &[PathBuf]| {
+        dirs[1..].iter().fold(dirs[0].clone(), |common, dir| {
+            common
+                .components()
+                .zip(dir.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect()
+        })
+    };
+
+    let lexical = common(&dirs);
+    let contains_all = dirs.iter().all(|dir| {
+        dir.strip_prefix(&lexical)
+            .is_ok_and(|rest| !rest.components().any(|c| c == Component::ParentDir))
+    });
+    if contains_all {
+        return if lexical.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            lexical
+        };
+    }
+    match dirs
+        .iter()
+        .map(|dir| dir.canonicalize())
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(dirs) => common(&dirs),
+        Err(_) => PathBuf::from("."),
+    }
+}
+
+/// Whether `path`, found under `root`, is in one of `dirs`, which are relative to `root`.
+pub fn is_within(path: &Path, root: &Path, dirs: &[String]) -> bool {
+    let path = path.strip_prefix(root).unwrap_or(path);
+    let path = path.strip_prefix(".").unwrap_or(path);
+    dirs.iter().any(|dir| {
+        let dir = from_repo_relative(Path::new("."), dir);
+        path.starts_with(dir.strip_prefix(".").unwrap_or(&dir))
+    })
+}
+
+/// Recognizes test files by `.gitignore`-style patterns, matched against paths relative to the root
+/// being scanned.
+pub struct TestFiles(Gitignore);
+
+impl TestFiles {
+    pub fn new(patterns: &[String]) -> anyhow::Result<Self> {
+        let mut builder = GitignoreBuilder::new("");
+        for pattern in patterns {
+            builder.add_line(None, pattern)?;
+        }
+        Ok(TestFiles(builder.build()?))
+    }
+
+    /// Whether `path`, found under `root`, is a test file or in a test directory.
+    pub fn contains(&self, path: &Path, root: &Path) -> bool {
+        let path = path.strip_prefix(root).unwrap_or(path);
+        let path = path.strip_prefix(".").unwrap_or(path);
+        if path.has_root() {
+            return false;
+        }
+        self.0.matched_path_or_any_parents(path, false).is_ignore()
+    }
+}
+
+/// Flattens `path` into a single file name, for writing one output file per source file.
+///
+/// Separators are escaped rather than replaced, so distinct paths never collide and the result
+/// can't traverse out of the directory it's written to.
+pub fn mangle(path: &Path) -> String {
+    let path = display_path(path);
+    let path = path.strip_prefix("./").unwrap_or(&path);
+
+    let mut mangled = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '%' => mangled.push_str("%25"),
+            '/' => mangled.push_str("%2F"),
+            '\\' => mangled.push_str("%5C"),
+            ':' => mangled.push_str("%3A"),
+            _ => mangled.push(c),
+        }
+    }
+    mangled
+}
+
+/// The directory containing `path`, truncated to at most `depth` components, for rolling up
+/// findings by directory. Files at the top level roll up to `.`.
+pub fn rollup(path: &Path, depth: usize) -> String {
+    let path = display_path(path);
+    let path = path.strip_prefix("./").unwrap_or(&path);
+
+    let mut directories = path.split('/').collect::<Vec<_>>();
+    directories.pop();
+    directories.truncate(depth);
+    if directories.is_empty() {
+        ".".to_owned()
+    } else {
+        directories.join("/")
+    }
+}
+
+/// `native_separator` is rewritten to `/`. On Windows, the verbatim prefix (`\\?\`) that
+/// canonicalization adds is stripped as well, leaving drive letters intact.
+fn normalize(path: &str, native_separator: char) -> String {
+    if native_separator == '/' {
+        return path.to_owned();
+    }
+
+    path.strip_prefix(r"\\?\")
+        .unwrap_or(path)
+        .replace(native_separator, "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_windows_paths() {
+        assert_eq!(normalize(r".\src\main.rs", '\\'), "./src/main.rs");
+        assert_eq!(normalize(r"src\main.rs", '\\'), "src/main.rs");
+        assert_eq!(
+            normalize(r"C:\Users\me\src\main.rs", '\\'),
+            "C:/Users/me/src/main.rs"
+        );
+        assert_eq!(normalize(r"\\?\D:\repo\a.rs", '\\'), "D:/repo/a.rs");
+        assert_eq!(
+            normalize("./already/forward.rs", '\\'),
+            "./already/forward.rs"
+        );
+    }
+
+    #[test]
+    fn normalize_unix_paths() {
+        assert_eq!(normalize("./src/main.rs", '/'), "./src/main.rs");
+        assert_eq!(
+            normalize("/home/me/src/main.rs", '/'),
+            "/home/me/src/main.rs"
+        );
+        // Backslashes are legal in Unix file names.
+        assert_eq!(normalize(r"./odd\name.rs", '/'), r"./odd\name.rs");
+    }
+
+    #[test]
+    fn relative_paths() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let main = manifest_dir.join("src").join("main.rs");
+
+        assert_eq!(
+            relative_to(&main, manifest_dir),
+            Path::new("src").join("main.rs")
+        );
+        assert_eq!(
+            relative_to(&main, &manifest_dir.join("..").join("report_todo")),
+            Path::new("src").join("main.rs")
+        );
+        assert_eq!(relative_to(&main, &manifest_dir.join("test_cases")), main);
+    }
+
+    #[test]
+    fn third_party_paths() {
+        let dirs = ["vendor".to_owned(), "third_party/lib/".to_owned()];
+
+        assert!(is_within(Path::new("./vendor/a.rs"), Path::new("."), &dirs));
+        assert!(is_within(
+            Path::new("repo/third_party/lib/b/c.rs"),
+            Path::new("repo"),
+            &dirs
+        ));
+        assert!(!is_within(
+            Path::new("./vendored/a.rs"),
+            Path::new("."),
+            &dirs
+        ));
+        assert!(!is_within(
+            Path::new("./src/vendor/a.rs"),
+            Path::new("."),
+            &dirs
+        ));
+        assert!(!is_within(
+            Path::new("./third_party/a.rs"),
+            Path::new("."),
+            &dirs
+        ));
+    }
+
+    #[test]
+    fn test_file_patterns() {
+        let patterns = ["tests/", "*_test.rs", "*.spec.ts"]
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect::<Vec<_>>();
+        let test_files = TestFiles::new(&patterns).unwrap();
+        let root = Path::new(".");
+
+        assert!(test_files.contains(Path::new("./tests/it.rs"), root));
+        assert!(test_files.contains(Path::new("./src/a/tests/b/c.rs"), root));
+        assert!(test_files.contains(Path::new("./src/parse_test.rs"), root));
+        assert!(test_files.contains(Path::new("repo/web/app.spec.ts"), Path::new("repo")));
+        assert!(!test_files.contains(Path::new("./src/tests.rs"), root));
+        assert!(!test_files.contains(Path::new("./src/latest/a.rs"), root));
+        assert!(!test_files.contains(Path::new("./web/app.ts"), root));
+    }
+
+    #[test]
+    fn mangle_paths() {
+        assert_eq!(mangle(Path::new("./src/main.rs")), "src%2Fmain.rs");
+        assert_eq!(mangle(Path::new("src/main.rs")), "src%2Fmain.rs");
+        assert_eq!(
+            mangle(Path::new("../../etc/passwd")),
+            "..%2F..%2Fetc%2Fpasswd"
+        );
+        assert_eq!(mangle(Path::new("/abs/a.rs")), "%2Fabs%2Fa.rs");
+        assert_ne!(mangle(Path::new("a%2Fb")), mangle(Path::new("a/b")));
+    }
+
+    #[test]
+    fn rollup_paths() {
+        assert_eq!(rollup(Path::new("./src/api/v1/a.rs"), 2), "src/api");
+        assert_eq!(rollup(Path::new("src/db/a.rs"), 2), "src/db");
+        assert_eq!(rollup(Path::new("src/main.rs"), 2), "src");
+        assert_eq!(rollup(Path::new("./Cargo.toml"), 2), ".");
+        assert_eq!(rollup(Path::new("src/api/a.rs"), 0), ".");
+    }
+
+    #[test]
+    fn repo_relative_paths() {
+        assert_eq!(
+            display_path(&from_repo_relative(Path::new("."), "src/checkers/mod.rs")),
+            "./src/checkers/mod.rs"
+        );
+        assert_eq!(
+            display_path(&from_repo_relative(Path::new("../.."), "Cargo.toml")),
+            "../../Cargo.toml"
+        );
+    }
+
+    #[test]
+    fn relative_roots() {
+        // Tests run in the package directory.
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(up_to(manifest_dir), Path::new("."));
+        assert_eq!(up_to(manifest_dir.parent().unwrap()), Path::new(".."));
+        let elsewhere = manifest_dir.join("src");
+        assert_eq!(up_to(&elsewhere), elsewhere);
+    }
+
+    #[test]
+    fn common_dirs() {
+        // Tests run in the package directory.
+        let common =
+            |roots: &[&str]| common_dir(&roots.iter().map(PathBuf::from).collect::<Vec<_>>());
+        assert_eq!(common(&["src"]), Path::new("src"));
+        assert_eq!(common(&["src/checkers", "src/paths.rs"]), Path::new("src"));
+        assert_eq!(common(&["src", "benches"]), Path::new("."));
+        assert_eq!(common(&["Cargo.toml"]), Path::new("."));
+        assert_eq!(common(&["./src", "./benches"]), Path::new("."));
+        assert_eq!(common(&["../span", "../report_todo/src"]), Path::new(".."));
+        assert_eq!(
+            common(&["../span", "src"]),
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .parent()
+                .unwrap()
+                .canonicalize()
+                .unwrap()
+        );
+    }
+}
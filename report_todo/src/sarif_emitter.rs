@@ -0,0 +1,231 @@
+//! SARIF 2.1.0, for uploading findings to code scanning, e.g. GitHub's Security tab.
+
+use std::{collections::BTreeSet, io::Write};
+
+use anyhow::{anyhow, Context};
+use serde_json::{json, Value};
+
+use crate::{
+    emitter::Emitter,
+    json_emitter::Metadata,
+    paths::display_path,
+    todo_error::{Severity, TodoError},
+};
+
+const SARIF_VERSION: &str = "2.1.0";
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// `--format sarif`.
+pub struct SarifEmitter;
+
+impl Emitter for SarifEmitter {
+    fn write_to(
+        &self,
+        out: &mut dyn Write,
+        findings: &[TodoError],
+        metadata: &Metadata,
+    ) -> anyhow::Result<()> {
+        write_to(out, findings, metadata)
+    }
+
+    fn validate(&self, output: &str, findings: &[TodoError]) -> anyhow::Result<()> {
+        validate(output, findings.len()).context("invalid SARIF log")
+    }
+}
+
+/// Writes all findings to `writer` as one SARIF log with a single run.
+pub fn write_to(
+    mut writer: impl Write,
+    findings: &[TodoError],
+    metadata: &Metadata,
+) -> anyhow::Result<()> {
+    serde_json::to_writer_pretty(&mut writer, &log(findings, metadata))?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// The SARIF log for `findings`. Rules are the distinct [`rule_id`]s of the findings. `metadata`
+/// is reported as the run's invocation, with the commit and config hash as its properties.
+fn log(findings: &[TodoError], metadata: &Metadata) -> Value {
+    let rules = findings
+        .iter()
+        .map(rule_id)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|rule_id| json!({ "id": rule_id }))
+        .collect::<Vec<_>>();
+    let results = findings.iter().map(result).collect::<Vec<_>>();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "report_todo",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/tangmi/report_todo",
+                    "rules": rules,
+                },
+            },
+            "invocations": [{
+                "executionSuccessful": true,
+                "startTimeUtc": metadata.timestamp,
+                "workingDirectory": { "uri": display_path(&metadata.root) },
+                "properties": {
+                    "commit": metadata.commit,
+                    "configHash": metadata.config_hash,
+                },
+            }],
+            "results": results,
+        }],
+    })
+}
+
+/// `untracked-todo` or `tracked-todo`. Unlike [`TodoError::rule_id`], not by keyword, so code
+/// scanning groups all findings into these two rules.
+fn rule_id(todo_error: &TodoError) -> &'static str {
+    if todo_error.is_tracked() {
+        "tracked-todo"
+    } else {
+        "untracked-todo"
+    }
+}
+
+fn result(todo_error: &TodoError) -> Value {
+    let level = match todo_error.severity() {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note",
+    };
+    let path = display_path(todo_error.file_path());
+    let mut physical_location = json!({
+        "artifactLocation": {
+            "uri": path.strip_prefix("./").unwrap_or(&path),
+            "uriBaseId": "%SRCROOT%",
+        },
+        "region": {
+            "startLine": todo_error.row(),
+            "startColumn": todo_error.col(),
+        },
+    });
+    // With `--embed-snippet`, the finding itself as the region's snippet, and its line as the
+    // context region's.
+    if let Some(snippet) = todo_error.snippet() {
+        let span = snippet
+            .line
+            .get(snippet.span_start..snippet.span_start + snippet.span_len)
+            .unwrap_or_default();
+        physical_location["region"]["snippet"] = json!({ "text": span });
+        physical_location["contextRegion"] = json!({
+            "startLine": todo_error.row(),
+            "snippet": { "text": snippet.line },
+        });
+    }
+
+    json!({
+        "ruleId": rule_id(todo_error),
+        "level": level,
+        "message": { "text": todo_error.message() },
+        "locations": [{ "physicalLocation": physical_location }],
+        "partialFingerprints": { "reportTodo/v1": todo_error.fingerprint() },
+    })
+}
+
+/// Checks the parts of `log` that code scanning relies on: the SARIF version, one run of
+/// `findings` results, and each result's rule and start line. This isn't validation against the
+/// SARIF schema, which isn't bundled.
+pub fn validate(log: &str, findings: usize) -> anyhow::Result<()> {
+    let log = serde_json::from_str::<Value>(log)?;
+    if log["version"] != SARIF_VERSION {
+        return Err(anyhow!("expected version {}", SARIF_VERSION));
+    }
+    let results = log["runs"][0]["results"]
+        .as_array()
+        .ok_or_else(|| anyhow!("expected a run with a results array"))?;
+    if results.len() != findings {
+        return Err(anyhow!("expected {} results", findings));
+    }
+    for result in results {
+        let region = &result["locations"][0]["physicalLocation"]["region"];
+        if !result["ruleId"].is_string() || !region["startLine"].is_u64() {
+            return Err(anyhow!("result is missing its rule or location"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn metadata() -> Metadata {
+        Metadata {
+            tool_version: "1.0.0",
+            timestamp: "2021-02-03T04:05:06Z".to_owned(),
+            root: PathBuf::from("./src"),
+            commit: Some("0123abc".to_owned()),
+            config_hash: "0".repeat(16),
+        }
+    }
+
+    #[test]
+    fn one_result_per_finding() {
+        let findings = [TodoError::sample(false), TodoError::sample(true)];
+        let log = log(&findings, &metadata());
+        let run = &log["runs"][0];
+
+        assert_eq!(
+            run["tool"]["driver"]["rules"],
+            json!([{ "id": "tracked-todo" }, { "id": "untracked-todo" }])
+        );
+        assert_eq!(run["results"][0]["ruleId"], "untracked-todo");
+        assert_eq!(run["results"][1]["ruleId"], "tracked-todo");
+        assert_eq!(run["results"][0]["level"], "error");
+        assert_eq!(run["results"][1]["level"], "note");
+        assert_eq!(
+            run["results"][0]["locations"][0]["physicalLocation"],
+            json!({
+                "artifactLocation": { "uri": "src/sample.rs", "uriBaseId": "%SRCROOT%" },
+                "region": { "startLine": 2, "startColumn": 8 },
+            })
+        );
+        assert!(validate(&log.to_string(), 2).is_ok());
+        assert!(validate(&log.to_string(), 1).is_err());
+    }
+
+    #[test]
+    fn embedded_snippets() {
+        let mut todo_error = TodoError::sample(false);
+        todo_error.embed_snippet();
+        let log = log(&[todo_error], &metadata());
+        let location = &log["runs"][0]["results"][0]["locations"][0]["physicalLocation"];
+
+        assert_eq!(
+            location["region"]["snippet"]["text"],
+            "TODO: untracked sample"
+        );
+        assert_eq!(location["contextRegion"]["startLine"], 2);
+        assert_eq!(
+            location["contextRegion"]["snippet"]["text"],
+            "// TODO: untracked sample"
+        );
+    }
+
+    #[test]
+    fn metadata_is_the_invocation() {
+        let log = log(&[], &metadata());
+
+        assert_eq!(
+            log["runs"][0]["invocations"],
+            json!([{
+                "executionSuccessful": true,
+                "startTimeUtc": "2021-02-03T04:05:06Z",
+                "workingDirectory": { "uri": "./src" },
+                "properties": { "commit": "0123abc", "configHash": "0000000000000000" },
+            }])
+        );
+    }
+}
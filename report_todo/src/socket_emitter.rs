@@ -0,0 +1,99 @@
+//! Streaming findings to a Unix socket or TCP endpoint, for editors and daemons to consume.
+
+use std::{
+    io::{BufWriter, Write},
+    net::TcpStream,
+    path::PathBuf,
+};
+
+use anyhow::Context;
+
+use crate::{json_emitter, todo_error::TodoError};
+
+#[derive(Debug)]
+pub enum Endpoint {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+/// Connects to `endpoint` and writes each finding to it as a line of JSON, flushing after each.
+pub fn stream(endpoint: &Endpoint, findings: &[TodoError]) -> anyhow::Result<()> {
+    match endpoint {
+        Endpoint::Unix(path) => {
+            let stream = connect_unix(path)
+                .with_context(|| format!("failed to connect to socket {}", path.display()))?;
+            write_all(stream, findings)
+        }
+        Endpoint::Tcp(address) => {
+            let stream = TcpStream::connect(address)
+                .with_context(|| format!("failed to connect to {}", address))?;
+            write_all(stream, findings)
+        }
+    }
+    .with_context(|| format!("failed to stream findings to {:?}", endpoint))
+}
+
+fn write_all(stream: impl Write, findings: &[TodoError]) -> anyhow::Result<()> {
+    let mut stream = BufWriter::new(stream);
+    for todo_error in findings {
+        json_emitter::write_line(&mut stream, todo_error)?;
+        stream.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn connect_unix(path: &std::path::Path) -> std::io::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(path)
+}
+
+#[cfg(not(unix))]
+fn connect_unix(_path: &std::path::Path) -> std::io::Result<std::fs::File> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Unix sockets aren't supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    #[test]
+    fn streams_lines_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let reader = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            BufReader::new(stream)
+                .lines()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+        });
+
+        let findings = [TodoError::sample(true), TodoError::sample(false)];
+        stream(&Endpoint::Tcp(address), &findings).unwrap();
+
+        let lines = reader.join().unwrap();
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            let finding = serde_json::from_str::<serde_json::Value>(&line).unwrap();
+            assert!(finding["rule_id"].is_string());
+        }
+    }
+
+    #[test]
+    fn connection_failure_is_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let error = stream(&Endpoint::Tcp(address.clone()), &[]).unwrap_err();
+        assert!(format!("{:#}", error).contains(&format!("failed to connect to {}", address)));
+    }
+}
@@ -0,0 +1,116 @@
+//! Markdown summary of findings for the GitHub Actions run page.
+
+use std::{fs::OpenOptions, io::Write};
+
+use anyhow::Context;
+
+use crate::{
+    emitter::Emitter,
+    json_emitter::{Metadata, Summary},
+    paths::display_path,
+    todo_error::{Severity, TodoError},
+};
+
+/// `--format step-summary`: a table of findings and the totals.
+pub struct StepSummaryEmitter {
+    pub summary: Summary,
+}
+
+impl Emitter for StepSummaryEmitter {
+    fn write_to(
+        &self,
+        mut out: &mut dyn Write,
+        findings: &[TodoError],
+        _metadata: &Metadata,
+    ) -> anyhow::Result<()> {
+        Ok(write_markdown(&mut out, findings, &self.summary)?)
+    }
+
+    /// Appends to the file named by `$GITHUB_STEP_SUMMARY`, or writes to stdout outside of GitHub
+    /// Actions.
+    fn finalize(&self, findings: &[TodoError], metadata: &Metadata) -> anyhow::Result<()> {
+        match std::env::var_os("GITHUB_STEP_SUMMARY") {
+            Some(path) => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .with_context(|| format!("failed to open {}", path.to_string_lossy()))?;
+                self.write_to(&mut file, findings, metadata)
+            }
+            None => {
+                let stdout = std::io::stdout();
+                self.write_to(&mut stdout.lock(), findings, metadata)
+            }
+        }
+    }
+}
+
+/// Writes the table of findings and the totals to `out`.
+pub fn write_markdown(
+    out: &mut impl Write,
+    findings: &[TodoError],
+    summary: &Summary,
+) -> std::io::Result<()> {
+    writeln!(out, "## report_todo")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{} issues found: {} untracked, {} warnings, {} tracked, {} suppressed.",
+        summary.issues_found,
+        summary.untracked,
+        summary.warnings,
+        summary.tracked,
+        summary.suppressed
+    )?;
+
+    if findings.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(out, "| File | Line | Severity | Message | Link |")?;
+    writeln!(out, "| --- | --- | --- | --- | --- |")?;
+    for todo_error in findings {
+        let severity = match todo_error.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "tracked",
+        };
+        let link = match (todo_error.tracking_id(), todo_error.link()) {
+            (Some(id), Some(link)) => format!("[{}]({})", escape_cell(id), link),
+            (Some(id), None) => escape_cell(id),
+            (None, _) => String::new(),
+        };
+        writeln!(
+            out,
+            "| `{}` | {} | {} | {} | {} |",
+            escape_cell(&display_path(todo_error.file_path())),
+            todo_error.row(),
+            severity,
+            escape_cell(todo_error.message()),
+            link
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Keeps `text` inside a single table cell.
+fn escape_cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_are_escaped() {
+        assert_eq!(escape_cell("a | b"), r"a \| b");
+        assert_eq!(escape_cell("a\nb"), "a b");
+        assert_eq!(escape_cell(r"C:\x"), r"C:\\x");
+    }
+}
@@ -1,38 +1,493 @@
 use crate::console_emitter::{ColoredWriter, Style};
+use crate::language::{CustomLanguage, Language};
+use crate::paths::{display_path, serialize_path};
+use anyhow::anyhow;
 use regex::Regex;
+use serde::Serialize;
 use span::*;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use termcolor::WriteColor;
+
+/// Pattern for [`Regexes::allow_marker`].
+pub const ALLOW_MARKER: &str = r"report[-_]todo:allow(?:\(([^)]*)\))?";
 
 #[derive(Debug)]
 pub struct Regexes {
-    /// Expects a single capture
-    pub match_issue: Regex,
+    /// Tried in order, for issue references in several trackers' conventions.
+    pub match_issues: Vec<IssuePattern>,
 
-    /// Expects a single string interpolation (`{replace_name}`) in which the capture from
-    pub issue_link_format: Option<String>,
+    /// Matches an issue referenced by its URL, e.g. `https://github.com/org/repo/issues/42`. A
+    /// keyword on the same line is then tracked.
+    pub match_issue_url: Option<Regex>,
 
     /// List of regexes of forbidden words
     pub bad_keywords: Vec<Regex>,
+
+    /// List of regexes of words reported as warnings rather than errors when untracked.
+    pub warning_keywords: Vec<Regex>,
+
+    /// Matches a `report-todo:allow(reason)` (or `report_todo:allow(reason)`) marker suppressing
+    /// the findings on its line. The reason is the first capture, and is optional.
+    pub allow_marker: Regex,
+
+    /// Apply `match_issue` across the following lines of a comment when a keyword's line doesn't
+    /// match on its own.
+    pub match_wrapped: bool,
+
+    /// Report at most one finding per line, the leftmost. Otherwise each keyword and macro is
+    /// reported once per line, at its first occurrence.
+    pub first_match_only: bool,
+
+    /// Names of the capture groups in `match_issue` and `match_issue_url` to read fields from.
+    pub groups: CaptureGroups,
+
+    /// In Rust files, invocations of these macros are reported by macro rather than by keyword.
+    pub rust_macros: Option<RustMacros>,
+
+    /// Languages from the config, detected before the built-in ones.
+    pub languages: Vec<Arc<CustomLanguage>>,
+
+    /// Skips the regexes for lines that can't contain a finding. Only set when every pattern that
+    /// can start a finding is a plain word.
+    pub prefilter: Option<LiteralPrefilter>,
+}
+
+/// How to report invocations of a Rust macro, e.g. `todo!()`. Parsed from `name=level`, where
+/// `level` is `error`, `warning` or `ignore`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RustMacro {
+    pub name: String,
+
+    /// `None` if invocations are ignored.
+    pub severity: Option<Severity>,
+}
+
+impl FromStr for RustMacro {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, level) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected `name=level`, got `{}`", s))?;
+        let severity = match level {
+            "error" => Some(Severity::Error),
+            "warning" => Some(Severity::Warning),
+            "ignore" => None,
+            _ => {
+                return Err(anyhow!(
+                    "unknown level `{}` for `{}!`; expected `error`, `warning` or `ignore`",
+                    level,
+                    name
+                ))
+            }
+        };
+        Ok(RustMacro {
+            name: name.trim_end_matches('!').to_owned(),
+            severity,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct RustMacros {
+    /// Matches an invocation of any of `macros`, capturing the name.
+    invocation: Regex,
+    macros: Vec<RustMacro>,
+}
+
+impl RustMacros {
+    pub fn new(macros: Vec<RustMacro>) -> Result<Self, regex::Error> {
+        let names = macros
+            .iter()
+            .map(|rust_macro| regex::escape(&rust_macro.name))
+            .collect::<Vec<_>>();
+        Ok(RustMacros {
+            invocation: Regex::new(&format!(r"\b({})!\s*[(\[{{]", names.join("|")))?,
+            macros,
+        })
+    }
+
+    /// Names of the macros that are reported rather than ignored.
+    pub fn reported_names(&self) -> impl Iterator<Item = &str> {
+        self.macros
+            .iter()
+            .filter(|rust_macro| rust_macro.severity.is_some())
+            .map(|rust_macro| rust_macro.name.as_str())
+    }
+
+    fn severity(&self, name: &str) -> Option<Severity> {
+        self.macros
+            .iter()
+            .find(|rust_macro| rust_macro.name == name)?
+            .severity
+    }
+}
+
+/// Whether `text` is a `YYYY-MM-DD` date, which compares in date order as a string.
+pub fn is_date(text: &str) -> bool {
+    text.len() == 10
+        && text.char_indices().all(|(index, c)| match index {
+            4 | 7 => c == '-',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+/// Cheap case-insensitive substring check for a set of ASCII words, used to reject most lines
+/// before running any regex.
+#[derive(Debug)]
+pub struct LiteralPrefilter {
+    /// Lowercased.
+    literals: Vec<Vec<u8>>,
+}
+
+impl LiteralPrefilter {
+    /// Returns `None` if any of `words` isn't made of ASCII word characters, and so needs the
+    /// regex engine.
+    pub fn new<'a>(words: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut literals = Vec::new();
+        for word in words {
+            if word.is_empty() || !word.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+                return None;
+            }
+            literals.push(word.to_ascii_lowercase().into_bytes());
+        }
+        Some(LiteralPrefilter { literals })
+    }
+
+    /// Whether `line` may contain one of the literals. Lines with non-ASCII text always may, as
+    /// the regexes' Unicode case folding can match them in ways this check doesn't.
+    pub fn may_match(&self, line: &str) -> bool {
+        if !line.is_ascii() {
+            return true;
+        }
+
+        let line = line.as_bytes();
+        self.literals.iter().any(|literal| {
+            let first = literal[0];
+            let mut offset = 0;
+            while let Some(i) = memchr::memchr2(first, first.to_ascii_uppercase(), &line[offset..])
+            {
+                let start = offset + i;
+                match line.get(start..start + literal.len()) {
+                    Some(candidate) if candidate.eq_ignore_ascii_case(literal) => return true,
+                    Some(_) => offset = start + 1,
+                    None => return false,
+                }
+            }
+            false
+        })
+    }
 }
 
+/// A `--match-issue` pattern, and the `--issue-link-format` for the issues it matches.
 #[derive(Debug)]
+pub struct IssuePattern {
+    /// Expects a capture for the tracking id, see [`CaptureGroups`].
+    pub regex: Regex,
+
+    /// Interpolated with the captures of `regex`, e.g. `${issue_number}`.
+    pub link_format: Option<String>,
+}
+
+impl IssuePattern {
+    /// Interpolates the captures of a match of `regex` into `link_format`.
+    fn link(&self, capture: &regex::Captures) -> Option<String> {
+        self.link_format.as_ref().map(|link_format| {
+            let mut link = String::new();
+            capture.expand(link_format, &mut link);
+            link.trim().to_owned()
+        })
+    }
+}
+
+impl Regexes {
+    /// The first of `match_issues` to match `text`, and its match.
+    fn match_issue<'t>(&self, text: &'t str) -> Option<(&IssuePattern, regex::Captures<'t>)> {
+        self.match_issues
+            .iter()
+            .find_map(|pattern| Some((pattern, pattern.regex.captures(text)?)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptureGroups {
+    /// Group holding the tracking id. Falls back to the first capture if the pattern doesn't define
+    /// it, and to the whole match if there are no captures at all.
+    pub id: String,
+    pub assignee: String,
+    pub due: String,
+    pub project: String,
+}
+
+impl Default for CaptureGroups {
+    fn default() -> Self {
+        Self {
+            id: "issue_number".to_owned(),
+            assignee: "assignee".to_owned(),
+            due: "due".to_owned(),
+            project: "project".to_owned(),
+        }
+    }
+}
+
+impl CaptureGroups {
+    fn tracking_id(&self, capture: &regex::Captures) -> String {
+        capture
+            .name(&self.id)
+            .or_else(|| capture.get(1))
+            .or_else(|| capture.get(0))
+            .unwrap()
+            .as_str()
+            .to_owned()
+    }
+
+    /// Optional fields, which are only set if `pattern` defines (and matched) their groups.
+    fn details(&self, pattern: &Regex, capture: &regex::Captures) -> IssueDetails {
+        let get = |name: &str| capture.name(name).map(|m| m.as_str().to_owned());
+        IssueDetails {
+            assignee: get(&self.assignee),
+            due: get(&self.due),
+            project: get(&self.project),
+            captures: pattern
+                .capture_names()
+                .flatten()
+                .filter_map(|name| Some((name.to_owned(), get(name)?)))
+                .collect(),
+        }
+    }
+}
+
+/// Optional fields of a tracked issue, read from named capture groups.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IssueDetails {
+    pub assignee: Option<String>,
+    pub due: Option<String>,
+    pub project: Option<String>,
+
+    /// Every named group that matched, including the ones above, for custom fields.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub captures: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// An untracked issue that fails the run.
+    Error,
+
+    /// An untracked issue that is reported, but doesn't fail the run.
+    Warning,
+
+    /// A tracked issue.
+    Info,
+}
+
+impl Severity {
+    /// The finding's state, as named in `--severity-map`.
+    fn state(self) -> &'static str {
+        match self {
+            Severity::Error => "untracked",
+            Severity::Warning => "warning",
+            Severity::Info => "tracked",
+        }
+    }
+
+    fn default_label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+}
+
+/// Severity labels to report each state as in structured output. Parsed from `state=label`
+/// entries, where `state` is `untracked`, `warning`, `tracked` or `overdue`. Unmapped states keep
+/// their default label; overdue findings are tracked ones, see [`TodoError::is_overdue`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SeverityMap {
+    labels: Vec<(Severity, String)>,
+    overdue: Option<String>,
+}
+
+impl SeverityMap {
+    pub fn parse<'a>(entries: impl IntoIterator<Item = &'a String>) -> anyhow::Result<Self> {
+        let mut severity_map = SeverityMap::default();
+        for entry in entries {
+            let (state, label) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected `state=label`, got `{}`", entry))?;
+            let severity = [Severity::Error, Severity::Warning, Severity::Info]
+                .iter()
+                .copied()
+                .find(|severity| severity.state() == state);
+            if severity.is_none() && state != "overdue" {
+                return Err(anyhow!(
+                    "unknown state `{}`; expected `untracked`, `warning`, `tracked` or `overdue`",
+                    state
+                ));
+            }
+            if label.is_empty() {
+                return Err(anyhow!("missing label for `{}`", state));
+            }
+            match severity {
+                Some(severity) => severity_map.labels.push((severity, label.to_owned())),
+                None => severity_map.overdue = Some(label.to_owned()),
+            }
+        }
+        Ok(severity_map)
+    }
+
+    fn label(&self, severity: Severity) -> &str {
+        self.labels
+            .iter()
+            .rev()
+            .find(|(mapped, _)| *mapped == severity)
+            .map_or(severity.default_label(), |(_, label)| label)
+    }
+}
+
+/// Where a finding came from, when several checkers' findings are merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Origin {
+    /// On a line added on the current branch.
+    DiffAdded,
+
+    /// Elsewhere in a file changed on the current branch.
+    FileExisting,
+
+    /// On a line removed on the current branch. Only tracked findings are reported from removed
+    /// lines, as their issue may be closeable.
+    DiffRemoved,
+}
+
+/// The line a finding is on, embedded in structured output with `--embed-snippet` so the report
+/// can be read without the source.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snippet {
+    /// The line, trimmed.
+    pub line: String,
+
+    /// Byte offset of the finding in `line`.
+    pub span_start: usize,
+
+    /// Byte length of the finding in `line`.
+    pub span_len: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct TodoError {
     /// An identifier tracking the issue, e.g. a GitHub issue number.
     tracking_id: Option<String>,
 
-    /// The line containing the issue, with no trailing whitespace
+    #[serde(flatten)]
+    details: IssueDetails,
+
+    #[serde(skip)]
+    severity: Severity,
+
+    /// `severity` as reported in structured output, see [`TodoError::relabel`].
+    #[serde(rename = "severity")]
+    severity_label: String,
+
+    /// The keyword that was matched, lowercased, e.g. `fixme`. `issue` if `match_issue` matched
+    /// without any configured keyword in it.
+    keyword: String,
+
+    /// `report-todo/<keyword>/tracked` or `report-todo/<keyword>/untracked`, for tools to group
+    /// and suppress findings by.
+    rule_id: String,
+
+    /// Identifies the finding across runs, for tools to track it by. See [`identify`].
+    id: String,
+
+    /// Identifies the finding across runs and issues, for tools to deduplicate by. See
+    /// [`identify`].
+    fingerprint: String,
+
+    /// The whole line containing the issue, without its line terminator.
+    #[serde(skip)]
     original_line: String,
 
     /// Length of just the matching issue span.
     span_len: usize,
 
+    /// Set by [`TodoError::embed_snippet`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<Snippet>,
+
+    /// For notebooks, the 1-based index of the cell the issue is in. `row` is then the line within
+    /// the cell.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cell: Option<usize>,
+
     row: usize,
     col: usize,
 
+    #[serde(serialize_with = "serialize_path")]
     file_path: PathBuf,
 
+    /// Detected from the file's path, if recognized.
+    #[serde(serialize_with = "serialize_language")]
+    language: Option<Language>,
+
     message: String,
     help_message: Option<String>,
+
+    /// The issue's URL, for tracked findings with a known link.
+    link: Option<String>,
+
+    /// Style problems with the comment, from [`TodoError::check_style`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    style: Vec<String>,
+
+    /// Set if the line has an allow marker, to the reason given there (possibly empty).
+    #[serde(skip)]
+    suppression: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin: Option<Origin>,
+
+    /// In a `--third-party-dir`, so reported as informational and only with `--all`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    third_party: bool,
+}
+
+#[cfg(test)]
+impl Regexes {
+    /// Config for tests, like the defaults but with `match_issue`, matched case-insensitively.
+    /// Tracked issues link to `https://example.com/issues/<issue_number>`, which is also matched
+    /// as `match_issue_url`.
+    pub fn for_tests(match_issue: &str) -> Regexes {
+        let case_insensitive = |pattern: &str| {
+            regex::RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .unwrap()
+        };
+        Regexes {
+            match_issues: vec![IssuePattern {
+                regex: case_insensitive(match_issue),
+                link_format: Some("https://example.com/issues/${issue_number}".to_owned()),
+            }],
+            match_issue_url: Some(
+                Regex::new(r"https://example\.com/issues/(?P<issue_number>\d+)").unwrap(),
+            ),
+            bad_keywords: vec![case_insensitive(r"\btodo\b")],
+            warning_keywords: Vec::new(),
+            allow_marker: case_insensitive(ALLOW_MARKER),
+            match_wrapped: false,
+            first_match_only: false,
+            groups: CaptureGroups::default(),
+            rust_macros: None,
+            languages: Vec::new(),
+            prefilter: None,
+        }
+    }
 }
 
 impl TodoError {
@@ -40,52 +495,399 @@ impl TodoError {
         self.tracking_id.is_some()
     }
 
+    pub fn tracking_id(&self) -> Option<&str> {
+        self.tracking_id.as_deref()
+    }
+
+    /// When the issue is due, as captured by [`CaptureGroups::due`].
+    pub fn due(&self) -> Option<&str> {
+        self.details.due.as_deref()
+    }
+
+    /// Whether this is tracked and [due](TodoError::due) before `today`, a `YYYY-MM-DD` date.
+    /// Due dates in any other format are never overdue.
+    pub fn is_overdue(&self, today: &str) -> bool {
+        self.is_tracked() && self.due().is_some_and(|due| is_date(due) && due < today)
+    }
+
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    pub fn rule_id(&self) -> &str {
+        &self.rule_id
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn link(&self) -> Option<&str> {
+        self.link.as_deref()
+    }
+
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    pub fn file_path(&self) -> &Path {
+        &self.file_path
+    }
+
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn is_suppressed(&self) -> bool {
+        self.suppression.is_some()
+    }
+
+    pub fn is_removed(&self) -> bool {
+        self.origin == Some(Origin::DiffRemoved)
+    }
+
+    pub fn cell(&self) -> Option<usize> {
+        self.cell
+    }
+
+    pub fn set_cell(&mut self, cell: usize) {
+        self.cell = Some(cell);
+    }
+
+    /// Suppresses the finding as an allow marker would, giving `reason`.
+    pub fn suppress(&mut self, reason: String) {
+        self.suppression = Some(reason);
+    }
+
+    /// Notes style problems with the finding's comment: trailing whitespace, tab indentation, and
+    /// a keyword right after the comment delimiter, e.g. `//TODO`.
+    pub fn check_style(&mut self) {
+        if self.original_line.ends_with(char::is_whitespace) {
+            self.style
+                .push("TODO comment has trailing whitespace".to_owned());
+        }
+        if self.original_line.starts_with('\t') {
+            self.style
+                .push("TODO comment is indented with tabs".to_owned());
+        }
+
+        let before = &self.original_line[..self.col - 1];
+        let delimiter = self.language.as_ref().and_then(Language::comment_delimiter);
+        if delimiter.is_some_and(|delimiter| before.ends_with(delimiter)) {
+            self.style
+                .push("missing space after comment delimiter".to_owned());
+        }
+    }
+
+    /// Reports the finding at `file_path` instead, e.g. relative to another directory. Its `id`
+    /// and `fingerprint` are unchanged.
+    pub fn set_file_path(&mut self, file_path: PathBuf) {
+        self.file_path = file_path;
+    }
+
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.origin = Some(origin);
+    }
+
+    pub fn snippet(&self) -> Option<&Snippet> {
+        self.snippet.as_ref()
+    }
+
+    /// Includes the finding's line and where on it the finding is in structured output.
+    pub fn embed_snippet(&mut self) {
+        let indent = self.original_line.len() - self.original_line.trim_start().len();
+        let line = self.original_line.trim();
+        self.snippet = Some(Snippet {
+            line: line.to_owned(),
+            span_start: (self.col - 1).saturating_sub(indent).min(line.len()),
+            span_len: self.span_len,
+        });
+    }
+
+    /// Reports this tracked finding as removed by the current branch, so that its issue may be
+    /// closeable. It keeps its `info` severity, which never fails the run.
+    pub fn mark_removed(&mut self) {
+        debug_assert!(self.is_tracked());
+        self.origin = Some(Origin::DiffRemoved);
+        self.rule_id = format!("report-todo/{}/removed", self.keyword);
+        self.message = format!(
+            "tracked {} removed, issue {} may be closeable",
+            self.keyword.to_uppercase(),
+            self.tracking_id.as_deref().unwrap_or_default()
+        );
+    }
+
+    pub fn is_third_party(&self) -> bool {
+        self.third_party
+    }
+
+    /// Reports a finding in third-party code as informational, so it never fails the run.
+    pub fn mark_third_party(&mut self) {
+        self.third_party = true;
+        self.severity = Severity::Info;
+        self.severity_label = Severity::Info.default_label().to_owned();
+    }
+
+    /// Reports an untracked error as a warning instead, so it doesn't fail the run.
+    pub fn downgrade(&mut self) {
+        if self.severity == Severity::Error {
+            self.severity = Severity::Warning;
+            self.severity_label = Severity::Warning.default_label().to_owned();
+        }
+    }
+
+    /// Sets the severity reported in structured output from `severity_map`, with overdue meaning
+    /// due before `today`, a `YYYY-MM-DD` date.
+    pub fn relabel(&mut self, severity_map: &SeverityMap, today: &str) {
+        self.severity_label = match &severity_map.overdue {
+            Some(label) if self.is_overdue(today) => label.clone(),
+            _ => severity_map.label(self.severity).to_owned(),
+        };
+    }
+
+    /// A made-up finding, tracked or untracked, for checking that an output format works.
+    pub fn sample(tracked: bool) -> TodoError {
+        let file_path = PathBuf::from("./src/sample.rs");
+        let (tracking_id, severity, line, message, link) = if tracked {
+            (
+                Some("1".to_owned()),
+                Severity::Info,
+                "    // TODO(#1): tracked sample",
+                "tracked sample".to_owned(),
+                Some("https://example.com/issues/1".to_owned()),
+            )
+        } else {
+            (
+                None,
+                Severity::Error,
+                "    // TODO: untracked sample",
+                "TODO found without issue number".to_owned(),
+                None,
+            )
+        };
+        let col = line.find("TODO").unwrap() + 1;
+        let (id, fingerprint) = identify(
+            &file_path,
+            "todo",
+            tracking_id.as_deref(),
+            if tracked { &message } else { &line[col - 1..] },
+        );
+        let details = IssueDetails {
+            captures: tracking_id
+                .iter()
+                .map(|tracking_id| ("issue_number".to_owned(), tracking_id.clone()))
+                .collect(),
+            ..IssueDetails::default()
+        };
+        TodoError {
+            tracking_id,
+            details,
+            severity_label: severity.default_label().to_owned(),
+            severity,
+            rule_id: rule_id("todo", tracked),
+            id,
+            fingerprint,
+            keyword: "todo".to_owned(),
+            original_line: line.to_owned(),
+            span_len: line[col - 1..].len(),
+            snippet: None,
+            cell: None,
+            style: Vec::new(),
+            row: if tracked { 1 } else { 2 },
+            col,
+            language: Language::from_path(&file_path),
+            file_path,
+            message,
+            help_message: link_help(&link),
+            link,
+            suppression: None,
+            origin: None,
+            third_party: false,
+        }
+    }
+
+    /// As [`TodoError::from_line_in`], detecting the language from `file_path`.
     pub fn from_line(config: &Regexes, file_path: &Path, line: &str, row: usize) -> Vec<TodoError> {
+        let language = Language::detect(file_path, &config.languages);
+        Self::from_line_in(config, file_path, language.as_ref(), line, row)
+    }
+
+    /// Findings in `line`, from a file in `language`, as detected once per file by the checker.
+    pub fn from_line_in(
+        config: &Regexes,
+        file_path: &Path,
+        language: Option<&Language>,
+        line: &str,
+        row: usize,
+    ) -> Vec<TodoError> {
+        if let Some(prefilter) = &config.prefilter {
+            if !prefilter.may_match(line) {
+                return Vec::new();
+            }
+        }
+
+        // Don't match keywords in the allow marker itself.
+        let (matched, suppression) = match config.allow_marker.captures(line) {
+            Some(capture) => (
+                &line[..capture.get(0).unwrap().start()],
+                Some(allow_reason(&capture)),
+            ),
+            None => (line, None),
+        };
+        // Keep a block comment's closing delimiter out of the message and underline.
+        let matched = strip_trailing_comment_delimiter(matched, language);
+
         let mut issues = Vec::new();
-        if let Some(capture) = config.match_issue.captures(line) {
+        if let Some((pattern, capture)) = config.match_issue(matched) {
+            let link = pattern.link(&capture);
             let (todo_start_index, todo_end_index) = {
                 let m = capture.get(0).unwrap();
                 (m.start(), m.end())
             };
+            let keyword = keyword_in(config, capture.get(0).unwrap().as_str());
+            let tracking_id = config.groups.tracking_id(&capture);
+            let message = matched
+                .get(todo_end_index + 1..)
+                .unwrap_or("")
+                .trim()
+                .to_owned();
+            let (id, fingerprint) = identify(file_path, &keyword, Some(&tracking_id), &message);
 
             issues.push(TodoError {
-                tracking_id: Some(capture.get(1).unwrap().as_str().to_owned()),
+                tracking_id: Some(tracking_id),
+                details: config.groups.details(&pattern.regex, &capture),
+                severity: Severity::Info,
+                severity_label: Severity::Info.default_label().to_owned(),
+                rule_id: rule_id(&keyword, true),
+                id,
+                fingerprint,
+                keyword,
                 file_path: file_path.to_owned(),
+                language: language.cloned(),
 
                 original_line: line.to_owned(),
-                span_len: line[todo_start_index..].trim().len(),
+                span_len: matched[todo_start_index..].trim().len(),
+                snippet: None,
                 row,
                 col: todo_start_index + 1,
 
-                message: line[todo_end_index + 1..].trim().to_owned(),
-                help_message: config.issue_link_format.as_ref().map(|issue_link_format| {
-                    format!(
-                        "link: {}",
-                        config
-                            .match_issue
-                            .replace(
-                                &line[todo_start_index..=todo_end_index],
-                                issue_link_format.as_str()
-                            )
-                            .trim()
-                    )
-                }),
+                message,
+                help_message: link_help(&link),
+                link,
+                suppression: suppression.clone(),
+                origin: None,
+                third_party: false,
+                cell: None,
+                style: Vec::new(),
             });
         } else {
-            for keyword in &config.bad_keywords {
-                if let Some(m) = keyword.find(line) {
+            let masked;
+            let matched = match &config.rust_macros {
+                Some(rust_macros) if language == Some(&Language::Rust) => {
+                    masked = macro_invocations(
+                        rust_macros,
+                        file_path,
+                        line,
+                        matched,
+                        row,
+                        &suppression,
+                        &mut issues,
+                    );
+                    masked.as_str()
+                }
+                _ => matched,
+            };
+
+            let issue_url = config.match_issue_url.as_ref().and_then(|match_issue_url| {
+                Some((match_issue_url, match_issue_url.captures(matched)?))
+            });
+
+            let keywords = config
+                .bad_keywords
+                .iter()
+                .map(|keyword| (keyword, Severity::Error))
+                .chain(
+                    config
+                        .warning_keywords
+                        .iter()
+                        .map(|keyword| (keyword, Severity::Warning)),
+                );
+            for (keyword, severity) in keywords {
+                if let Some(capture) = keyword.captures(matched) {
+                    let m = capture.get(0).unwrap();
+                    let keyword = keyword_name(&capture);
+                    if let Some((match_issue_url, url)) = &issue_url {
+                        let tracking_id = config.groups.tracking_id(url);
+                        let message = matched[m.end()..]
+                            .trim_start_matches(|c: char| c == ':' || c.is_whitespace())
+                            .trim_end()
+                            .to_owned();
+                        let (id, fingerprint) =
+                            identify(file_path, &keyword, Some(&tracking_id), &message);
+                        issues.push(TodoError {
+                            tracking_id: Some(tracking_id),
+                            details: config.groups.details(match_issue_url, url),
+                            severity: Severity::Info,
+                            severity_label: Severity::Info.default_label().to_owned(),
+                            rule_id: rule_id(&keyword, true),
+                            id,
+                            fingerprint,
+                            keyword,
+
+                            original_line: line.to_owned(),
+                            span_len: matched[m.start()..].trim().len(),
+                            snippet: None,
+                            row,
+                            col: m.start() + 1,
+
+                            file_path: file_path.to_owned(),
+                            language: language.cloned(),
+                            message,
+                            help_message: Some(format!("link: {}", url.get(0).unwrap().as_str())),
+                            link: Some(url.get(0).unwrap().as_str().to_owned()),
+                            suppression: suppression.clone(),
+                            origin: None,
+                            third_party: false,
+                            cell: None,
+                            style: Vec::new(),
+                        });
+                        continue;
+                    }
+
+                    let (id, fingerprint) =
+                        identify(file_path, &keyword, None, &matched[m.start()..]);
                     issues.push(TodoError {
                         tracking_id: None,
+                        details: IssueDetails::default(),
+                        severity_label: severity.default_label().to_owned(),
+                        severity,
+                        rule_id: rule_id(&keyword, false),
+                        id,
+                        fingerprint,
+                        keyword,
 
                         original_line: line.to_owned(),
-                        span_len: line[m.start()..].trim().len(),
+                        span_len: matched[m.start()..].trim().len(),
+                        snippet: None,
                         row,
                         col: m.range().start + 1,
 
                         file_path: file_path.to_owned(),
+                        language: language.cloned(),
                         message: format!(
                             "{} found without issue number",
-                            m.as_str().to_uppercase()
+                            capture
+                                .name("keyword")
+                                .map_or(m.as_str(), |keyword| keyword.as_str())
+                                .to_uppercase()
                         ),
 
                         // TODO(#7): Try and generate an example from `config.match_issue` regex?
@@ -93,32 +895,495 @@ impl TodoError {
                             "help: create a work item and reference it here (e.g. `TODO(#1): ...`)"
                                 .to_owned(),
                         ),
+                        link: None,
+                        suppression: suppression.clone(),
+                        origin: None,
+                        third_party: false,
+                        cell: None,
+                        style: Vec::new(),
                     });
                 }
             }
         }
 
+        if config.first_match_only {
+            issues.sort_by_key(|issue| issue.col);
+            issues.truncate(1);
+        }
+        issues
+    }
+
+    /// As [`TodoError::from_line`], but also suppressed by an allow marker alone on the line
+    /// before, like `// eslint-disable-next-line`.
+    pub fn from_line_after(
+        config: &Regexes,
+        file_path: &Path,
+        language: Option<&Language>,
+        previous_line: Option<&str>,
+        line: &str,
+        row: usize,
+    ) -> Vec<TodoError> {
+        let mut issues = Self::from_line_in(config, file_path, language, line, row);
+        let reason = previous_line.and_then(|previous_line| {
+            let capture = config.allow_marker.captures(previous_line)?;
+            let before = &previous_line[..capture.get(0).unwrap().start()];
+            strip_comment_delimiter(before)
+                .is_empty()
+                .then(|| allow_reason(&capture))
+        });
+        if let Some(reason) = reason {
+            for issue in issues.iter_mut().filter(|issue| !issue.is_suppressed()) {
+                issue.suppress(reason.clone());
+            }
+        }
         issues
     }
 
     /// `comment` is potentially multiline.
-    pub fn from_comment(config: &Regexes, file_path: &Path, comment: Span) -> Vec<TodoError> {
-        comment
+    pub fn from_comment(
+        config: &Regexes,
+        file_path: &Path,
+        language: Option<&Language>,
+        comment: Span,
+    ) -> Vec<TodoError> {
+        let lines = comment
             .lines_span()
             .filter(|line| !line.as_str().trim().is_empty())
-            .flat_map(|line| {
-                Self::from_line(
-                    config,
-                    file_path,
-                    line.as_str(),
-                    line.start_pos().line_col().0,
-                )
-            })
-            .collect()
+            .collect::<Vec<_>>();
+
+        let mut issues = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            let (row, col) = line.start_pos().line_col();
+            let physical_line = line.start_pos().line_of();
+            // The comment may start partway along the line, after code.
+            let prefix_len = physical_line
+                .char_indices()
+                .nth(col - 1)
+                .map_or(physical_line.len(), |(index, _)| index);
+            let mut line_issues = Self::from_line_after(
+                config,
+                file_path,
+                language,
+                i.checked_sub(1).map(|previous| lines[previous].as_str()),
+                line.as_str(),
+                row,
+            );
+
+            if config.match_wrapped && line_issues.iter().any(|issue| !issue.is_tracked()) {
+                if let Some(issue) =
+                    Self::from_wrapped_lines(config, file_path, language, &lines[i..])
+                {
+                    line_issues = vec![issue];
+                }
+            }
+
+            for mut issue in line_issues {
+                issue.original_line = without_line_terminator(physical_line).to_owned();
+                issue.col += prefix_len;
+                issues.push(issue);
+            }
+        }
+
+        issues
+    }
+
+    /// Matches `match_issue` against `lines` joined into one line, with the comment delimiters of
+    /// the continuation lines stripped. The match must start on the first line, which is where the
+    /// issue is reported.
+    fn from_wrapped_lines(
+        config: &Regexes,
+        file_path: &Path,
+        language: Option<&Language>,
+        lines: &[Span],
+    ) -> Option<TodoError> {
+        let first_line = lines[0].as_str().trim_end();
+        let mut joined = first_line.to_owned();
+        for line in &lines[1..] {
+            joined.push(' ');
+            joined.push_str(strip_comment_delimiter(line.as_str()));
+        }
+
+        let (pattern, capture) = config.match_issue(&joined)?;
+        let m = capture.get(0).unwrap();
+        if m.start() >= first_line.len() {
+            return None;
+        }
+        let link = pattern.link(&capture);
+        let keyword = keyword_in(config, m.as_str());
+        let tracking_id = config.groups.tracking_id(&capture);
+        let message = joined[m.end()..].trim().to_owned();
+        let (id, fingerprint) = identify(file_path, &keyword, Some(&tracking_id), &message);
+
+        Some(TodoError {
+            tracking_id: Some(tracking_id),
+            details: config.groups.details(&pattern.regex, &capture),
+            severity: Severity::Info,
+            severity_label: Severity::Info.default_label().to_owned(),
+            rule_id: rule_id(&keyword, true),
+            id,
+            fingerprint,
+            keyword,
+            file_path: file_path.to_owned(),
+            language: language.cloned(),
+
+            original_line: first_line.to_owned(),
+            span_len: first_line[m.start()..].trim().len(),
+            snippet: None,
+            row: lines[0].start_pos().line_col().0,
+            col: m.start() + 1,
+
+            message,
+            help_message: link_help(&link),
+            link,
+            suppression: None,
+            origin: None,
+            third_party: false,
+            cell: None,
+            style: Vec::new(),
+        })
     }
 }
 
-impl ColoredWriter {
+/// `line` without its trailing `\n` or `\r\n`.
+fn without_line_terminator(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// Adds findings for the invocations of `rust_macros` in `matched` (the part of `line` that
+/// findings are looked for in) to `issues`, skipping any in string literals or comments. Returns
+/// `matched` with the macro names blanked out, so that they aren't found again as keywords.
+fn macro_invocations(
+    rust_macros: &RustMacros,
+    file_path: &Path,
+    line: &str,
+    matched: &str,
+    row: usize,
+    suppression: &Option<String>,
+    issues: &mut Vec<TodoError>,
+) -> String {
+    let mut masked = matched.to_owned();
+    let code = blank_rust_literals_and_comments(matched);
+    for capture in rust_macros.invocation.captures_iter(&code) {
+        let name = capture.get(1).unwrap();
+        masked.replace_range(name.range(), &" ".repeat(name.as_str().len()));
+        let invocation_end = closing_delimiter(&code, capture.get(0).unwrap().end() - 1)
+            .map_or_else(|| code.trim_end().len(), |index| index + 1);
+
+        let severity = match rust_macros.severity(name.as_str()) {
+            Some(severity) => severity,
+            None => continue,
+        };
+        let keyword = format!("{}-macro", name.as_str());
+        let (id, fingerprint) = identify(file_path, &keyword, None, &matched[name.start()..]);
+        issues.push(TodoError {
+            tracking_id: None,
+            details: IssueDetails::default(),
+            severity_label: severity.default_label().to_owned(),
+            severity,
+            rule_id: rule_id(&keyword, false),
+            id,
+            fingerprint,
+            keyword,
+
+            original_line: line.to_owned(),
+            span_len: invocation_end - name.start(),
+            snippet: None,
+            row,
+            col: name.start() + 1,
+
+            file_path: file_path.to_owned(),
+            language: Some(Language::Rust),
+            message: format!("`{}!()` macro invocation", name.as_str()),
+            help_message: Some(
+                match name.as_str() {
+                    "unimplemented" => {
+                        "help: if this stub is intentional, say why with \
+                        `report-todo:allow(reason)`"
+                    }
+                    "unreachable" => {
+                        "help: this asserts the code can't be reached; if that's intended, say \
+                        why with `report-todo:allow(reason)`"
+                    }
+                    _ => {
+                        "help: create a work item and link it in a comment here (e.g. \
+                        `// TODO(#1): ...`), then replace this with `unimplemented!()`"
+                    }
+                }
+                .to_owned(),
+            ),
+            link: None,
+            suppression: suppression.clone(),
+            origin: None,
+            third_party: false,
+            cell: None,
+            style: Vec::new(),
+        });
+    }
+    masked
+}
+
+/// `line` with its Rust string and char literals and comments replaced by spaces, so that what's
+/// left is code at the same byte offsets. Only this line is looked at: a string or block comment
+/// that starts on an earlier line isn't recognized.
+fn blank_rust_literals_and_comments(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let mut blanked = line.to_owned();
+    let mut index = 0;
+    while index < bytes.len() {
+        let end = match bytes[index] {
+            b'/' if bytes.get(index + 1) == Some(&b'/') => Some(bytes.len()),
+            b'/' if bytes.get(index + 1) == Some(&b'*') => Some(block_comment_end(bytes, index)),
+            b'"' => Some(string_end(bytes, index)),
+            b'\'' => char_literal_end(line, index),
+            b'b' | b'r' if index == 0 || !is_identifier_byte(bytes[index - 1]) => {
+                raw_string_end(bytes, index)
+            }
+            _ => None,
+        };
+        match end {
+            Some(end) => {
+                blanked.replace_range(index..end, &" ".repeat(end - index));
+                index = end;
+            }
+            None => index += 1,
+        }
+    }
+    blanked
+}
+
+fn is_identifier_byte(byte: u8) -> bool {
+    byte == b'_' || byte.is_ascii_alphanumeric()
+}
+
+/// The end of the (possibly nested) block comment starting at `start`, or of `bytes`.
+fn block_comment_end(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut index = start;
+    while index + 1 < bytes.len() {
+        match &bytes[index..index + 2] {
+            b"/*" => {
+                depth += 1;
+                index += 2;
+            }
+            b"*/" => {
+                depth -= 1;
+                index += 2;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => index += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// The end of the string literal whose opening quote is at `start`, or of `bytes`.
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let mut index = start + 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'"' => return index + 1,
+            _ => index += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// The end of the char literal whose opening quote is at `start`, or `None` for a lifetime.
+fn char_literal_end(line: &str, start: usize) -> Option<usize> {
+    let rest = &line[start + 1..];
+    if rest.starts_with('\\') {
+        // Skip the escaped character, which may itself be a quote.
+        let index = rest.get(2..)?.find('\'')?;
+        return Some(start + 1 + 2 + index + 1);
+    }
+    let c = rest.chars().next()?;
+    rest[c.len_utf8()..]
+        .starts_with('\'')
+        .then_some(start + 1 + c.len_utf8() + 1)
+}
+
+/// The end of the `r"..."`, `r#"..."#` or `br"..."` literal starting at `start`, or `None` if
+/// there's no raw string there.
+fn raw_string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut index = start;
+    if bytes[index] == b'b' {
+        index += 1;
+    }
+    if bytes.get(index) != Some(&b'r') {
+        return None;
+    }
+    index += 1;
+    let hashes = bytes[index..]
+        .iter()
+        .take_while(|&&byte| byte == b'#')
+        .count();
+    index += hashes;
+    if bytes.get(index) != Some(&b'"') {
+        return None;
+    }
+    let closing = [&b"\""[..], &vec![b'#'; hashes]].concat();
+    Some(
+        bytes[index + 1..]
+            .windows(closing.len())
+            .position(|window| window == closing.as_slice())
+            .map_or(bytes.len(), |position| index + 1 + position + closing.len()),
+    )
+}
+
+/// The index of the delimiter closing the `(`, `[` or `{` at `open` in `code`, if it's on this
+/// line.
+fn closing_delimiter(code: &str, open: usize) -> Option<usize> {
+    let bytes = code.as_bytes();
+    let close = match bytes[open] {
+        b'(' => b')',
+        b'[' => b']',
+        _ => b'}',
+    };
+    let mut depth = 0;
+    for (index, &byte) in bytes.iter().enumerate().skip(open) {
+        if byte == bytes[open] {
+            depth += 1;
+        } else if byte == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(index);
+            }
+        }
+    }
+    None
+}
+
+/// The first configured keyword in `text`, normalized.
+fn keyword_in(config: &Regexes, text: &str) -> String {
+    config
+        .bad_keywords
+        .iter()
+        .chain(&config.warning_keywords)
+        .find_map(|keyword| keyword.captures(text))
+        .map_or_else(|| "issue".to_owned(), |capture| keyword_name(&capture))
+}
+
+/// The keyword matched by a keyword regex, without any variant suffix, normalized.
+fn keyword_name(capture: &regex::Captures) -> String {
+    let keyword = capture
+        .name("keyword")
+        .unwrap_or_else(|| capture.get(0).unwrap());
+    normalize_keyword(keyword.as_str())
+}
+
+/// Lowercases `keyword` and replaces anything but letters, digits, `_` and `-` with `-`, so it can
+/// be used in a rule id.
+fn normalize_keyword(keyword: &str) -> String {
+    keyword
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn rule_id(keyword: &str, tracked: bool) -> String {
+    format!(
+        "report-todo/{}/{}",
+        keyword,
+        if tracked { "tracked" } else { "untracked" }
+    )
+}
+
+/// Stable identifiers for a finding, as `(id, fingerprint)`. Both are hashes of its file path,
+/// keyword and message (with whitespace collapsed), but not its line or column, so that they
+/// survive code moving around the file. Untracked findings' messages are generic, so their text
+/// from the keyword on is used instead.
+///
+/// The id also hashes the tracking id, so that the same message tracked by different issues gets
+/// different ids, while its fingerprint stays the same when it's re-tracked.
+fn identify(
+    file_path: &Path,
+    keyword: &str,
+    tracking_id: Option<&str>,
+    message: &str,
+) -> (String, String) {
+    let path = display_path(file_path);
+    let path = path.strip_prefix("./").unwrap_or(&path);
+    let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+    (
+        stable_hash(&[path, keyword, tracking_id.unwrap_or(""), &message]),
+        stable_hash(&[path, keyword, &message]),
+    )
+}
+
+/// 64-bit FNV-1a of `parts`, which unlike `std`'s hashers is stable across Rust releases.
+pub fn stable_hash(parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        // Separate the parts so that e.g. moving a character from one to the next changes the
+        // hash.
+        for byte in part.bytes().chain(Some(0)) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// Built-in languages as their kebab-case name, custom ones as their configured name.
+fn serialize_language<S: serde::Serializer>(
+    language: &Option<Language>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match language.as_ref().and_then(Language::name) {
+        Some(name) => serializer.serialize_some(name),
+        None => language.serialize(serializer),
+    }
+}
+
+fn link_help(link: &Option<String>) -> Option<String> {
+    link.as_ref().map(|link| format!("link: {}", link))
+}
+
+/// Strips trailing whitespace and `language`'s closing block comment delimiter, if it has block
+/// comments. In a file of unknown language, strips either of the common `*/` and `-->`.
+fn strip_trailing_comment_delimiter<'a>(line: &'a str, language: Option<&Language>) -> &'a str {
+    let line = line.trim_end();
+    let stripped = match language {
+        Some(language) => language
+            .block_comment_end()
+            .and_then(|end| line.strip_suffix(end)),
+        None => ["*/", "-->"]
+            .iter()
+            .find_map(|delimiter| line.strip_suffix(delimiter)),
+    };
+    stripped.unwrap_or(line).trim_end()
+}
+
+/// The reason given in an allow marker, e.g. `report-todo:allow(example)`, or empty.
+fn allow_reason(capture: &regex::Captures) -> String {
+    capture
+        .get(1)
+        .map_or("", |reason| reason.as_str().trim())
+        .to_owned()
+}
+
+/// Strips leading whitespace and a line comment delimiter (or block comment continuation).
+fn strip_comment_delimiter(line: &str) -> &str {
+    let line = line.trim();
+    ["///", "//!", "//", "/*", "*", "#", "--", ";"]
+        .iter()
+        .find_map(|delimiter| line.strip_prefix(delimiter))
+        .unwrap_or(line)
+        .trim()
+}
+
+impl<W: WriteColor> ColoredWriter<W> {
     pub fn write_error(&mut self, todo: &TodoError) -> std::io::Result<()> {
         let line_trimmed = todo.original_line.trim();
         let display_col = todo.col
@@ -128,24 +1393,42 @@ impl ColoredWriter {
                 .unwrap_or(0);
 
         let spacing = " ".repeat(format!("{}", todo.row).len());
-        let underline = " ".repeat(display_col - 1)
-            + &"^".repeat({
-                // `.trim()` ignores the newline characters
-                todo.span_len
-            });
+        let (line_shown, underline_start, underline_len) = snippet_window(
+            line_trimmed,
+            display_col - 1,
+            // `.trim()` ignores the newline characters
+            todo.span_len,
+            self.max_width,
+        );
+        let underline = " ".repeat(underline_start) + &"^".repeat(underline_len);
 
         match &todo.tracking_id {
+            None if todo.severity == Severity::Warning => self.write("warning", Style::Warning)?,
             None => self.write("error", Style::Error)?,
 
             // TODO(#7): find a way to preserve user-configured pattern?
             Some(issue) => self.write(format!("TODO(#{})", issue), Style::Info)?,
         }
-        self.write(format!(": {}\n", todo.message), Style::Bold)?;
+        let assigned = todo
+            .details
+            .assignee
+            .as_ref()
+            .map_or_else(String::new, |assignee| {
+                format!(" (assigned to @{})", assignee.trim_start_matches('@'))
+            });
+        self.write(format!(": {}{}\n", todo.message, assigned), Style::Bold)?;
         self.write(format!("{}--> ", spacing), Style::LineNumber)?;
         self.write(
             format!(
-                "{p}{l}:{c}\n",
-                p = format!("{}:", todo.file_path.display()),
+                "{p}{cell}{l}:{c}\n",
+                p = if self.file_urls {
+                    format!("{}:", file_url(&todo.file_path))
+                } else {
+                    format!("{}:", display_path(&todo.file_path))
+                },
+                cell = todo
+                    .cell
+                    .map_or_else(String::new, |cell| format!("cell {}:", cell)),
                 l = todo.row,
                 c = todo.col,
             ),
@@ -153,23 +1436,821 @@ impl ColoredWriter {
         )?;
         self.write(format!("{} |\n", spacing), Style::LineNumber)?;
         self.write(format!("{} | ", todo.row), Style::LineNumber)?;
-        self.write(format!("{}\n", line_trimmed), Style::Normal)?;
+        self.write(format!("{}\n", line_shown), Style::Normal)?;
         self.write(format!("{} | ", spacing), Style::LineNumber)?;
         self.write(
             format!("{}\n", underline),
-            if todo.tracking_id.is_some() {
-                Style::Info
-            } else {
-                Style::Error
+            match todo.severity {
+                Severity::Error => Style::Error,
+                Severity::Warning => Style::Warning,
+                Severity::Info => Style::Info,
             },
         )?;
         self.write(format!("{} |\n", spacing), Style::LineNumber)?;
-        if let Some(help_message) = &todo.help_message {
+        // Untracked findings' help is generic, while tracked findings' links differ.
+        let help_message = todo
+            .help_message
+            .as_ref()
+            .filter(|help_message| todo.is_tracked() || !self.fold_help(help_message));
+        if let Some(help_message) = help_message {
             self.write(format!("{} = ", spacing), Style::LineNumber)?;
             self.write(format!("{}\n", help_message), Style::Normal)?;
         }
+        for style in &todo.style {
+            self.write(format!("{} = ", spacing), Style::LineNumber)?;
+            self.write(format!("style: {}\n", style), Style::Normal)?;
+        }
+        if todo.origin == Some(Origin::FileExisting) {
+            self.write(format!("{} = ", spacing), Style::LineNumber)?;
+            self.write(
+                "note: in a file changed on this branch, but not on a changed line\n",
+                Style::Normal,
+            )?;
+        }
+        if todo.third_party {
+            self.write(format!("{} = ", spacing), Style::LineNumber)?;
+            self.write(
+                "note: in a third-party directory, so it doesn't fail the run\n",
+                Style::Normal,
+            )?;
+        }
+        if todo.origin == Some(Origin::DiffRemoved) {
+            self.write(format!("{} = ", spacing), Style::LineNumber)?;
+            self.write(
+                "note: removed on this branch; the line number is from before the change\n",
+                Style::Normal,
+            )?;
+        }
+        if let Some(reason) = &todo.suppression {
+            self.write(format!("{} = ", spacing), Style::LineNumber)?;
+            if reason.is_empty() {
+                self.write("allowed (no reason given)\n", Style::Normal)?;
+            } else {
+                self.write(format!("allowed: {}\n", reason), Style::Normal)?;
+            }
+        }
         self.write("\n", Style::Normal)?;
 
         Ok(())
     }
 }
+
+/// Cuts `line` down to at most `max_width` characters around the span starting at byte `start`,
+/// marking cut ends with `…`, so minified or generated lines don't flood the terminal. Returns the
+/// text to show and the span's start and length within it, in characters.
+fn snippet_window(
+    line: &str,
+    start: usize,
+    span_len: usize,
+    max_width: usize,
+) -> (String, usize, usize) {
+    let start = start.min(line.len());
+    let span_end = (start + span_len).min(line.len());
+    let chars_before = line
+        .get(..start)
+        .map_or(start, |before| before.chars().count());
+    let span_chars = line
+        .get(start..span_end)
+        .map_or(span_len, |span| span.chars().count());
+
+    let line_chars = line.chars().count();
+    if line_chars <= max_width {
+        return (line.to_owned(), chars_before, span_chars);
+    }
+
+    // Leave room for an ellipsis at each end, and some context before the span.
+    let width = max_width.saturating_sub(2).max(1);
+    let window_start = chars_before.saturating_sub(width / 4);
+    let window_end = (window_start + width).min(line_chars);
+
+    let mut shown = String::new();
+    if window_start > 0 {
+        shown.push('…');
+    }
+    shown.extend(
+        line.chars()
+            .skip(window_start)
+            .take(window_end - window_start),
+    );
+    if window_end < line_chars {
+        shown.push('…');
+    }
+
+    let underline_start = chars_before - window_start + usize::from(window_start > 0);
+    let underline_len = span_chars.min(window_end.saturating_sub(chars_before));
+    (shown, underline_start, underline_len)
+}
+
+/// Formats `path` as an absolute `file://` URL, which many terminals make clickable.
+///
+/// Falls back to the path as given if it can't be canonicalized.
+fn file_url(path: &Path) -> String {
+    let absolute = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let absolute = display_path(&absolute).replace(' ', "%20");
+
+    if absolute.starts_with('/') {
+        format!("file://{}", absolute)
+    } else {
+        format!("file:///{}", absolute)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regexes(match_issue: &str, match_wrapped: bool) -> Regexes {
+        Regexes {
+            match_wrapped,
+            ..Regexes::for_tests(match_issue)
+        }
+    }
+
+    /// Renders `todo_errors` as `write_error` does, without colors.
+    fn render(todo_errors: &[TodoError]) -> String {
+        let mut writer = ColoredWriter::buffer();
+        for todo_error in todo_errors {
+            writer.write_error(todo_error).unwrap();
+        }
+        String::from_utf8(writer.into_inner().into_inner()).unwrap()
+    }
+
+    macro_rules! golden {
+        ($name:literal) => {
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/test_cases/golden/",
+                $name,
+                ".txt"
+            ))
+        };
+    }
+
+    fn from_comment(config: &Regexes, comment: &str) -> Vec<TodoError> {
+        let span = Span::new(comment, 0, comment.len()).unwrap();
+        TodoError::from_comment(config, Path::new("a.rs"), Some(&Language::Rust), span)
+    }
+
+    #[test]
+    fn render_untracked() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let issues = TodoError::from_line(&config, Path::new("./src/a.rs"), "// TODO: later", 7);
+        assert_eq!(render(&issues), golden!("untracked"));
+    }
+
+    #[test]
+    fn render_tracked() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let issues = TodoError::from_line(
+            &config,
+            Path::new("./src/a.rs"),
+            "let x = 1; // TODO(#42): tidy up",
+            12,
+        );
+        assert_eq!(render(&issues), golden!("tracked"));
+    }
+
+    #[test]
+    fn render_indented() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let issues = TodoError::from_line(
+            &config,
+            Path::new("./src/a.rs"),
+            "        // TODO: nested deeply",
+            104,
+        );
+        assert_eq!(render(&issues), golden!("indented"));
+    }
+
+    #[test]
+    fn render_multibyte() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let issues = TodoError::from_line(
+            &config,
+            Path::new("./src/ü.rs"),
+            "    let s = \"naïve\"; // TODO: café ☕",
+            3,
+        );
+        assert_eq!(render(&issues), golden!("multibyte"));
+    }
+
+    #[test]
+    fn style_checks() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let style = |path: &str, line: &str| {
+            let mut todo_error = TodoError::from_line(&config, Path::new(path), line, 1).remove(0);
+            todo_error.check_style();
+            todo_error.style
+        };
+
+        assert!(style("a.rs", "    // TODO: fine").is_empty());
+        assert!(style("a.py", "x = 1  # TODO(#1): fine").is_empty());
+        assert_eq!(
+            style("a.rs", "//TODO: a"),
+            vec!["missing space after comment delimiter"]
+        );
+        assert_eq!(
+            style("a.py", "#TODO: a"),
+            vec!["missing space after comment delimiter"]
+        );
+        assert!(style("a.py", "//TODO: a").is_empty());
+        assert!(style("a.unknown", "//TODO: a").is_empty());
+        assert_eq!(
+            style("a.rs", "\t// TODO: a "),
+            vec![
+                "TODO comment has trailing whitespace",
+                "TODO comment is indented with tabs"
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_language_delimiters() {
+        let mut config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        config.languages = vec![Arc::new(CustomLanguage {
+            name: None,
+            extensions: vec!["dsl".to_owned()],
+            line_comment: Some(";;".to_owned()),
+            block_comment: Some(("#|".to_owned(), "|#".to_owned())),
+        })];
+
+        let mut todo_error =
+            TodoError::from_line(&config, Path::new("a.dsl"), "(f) ;;TODO(#1): x", 1).remove(0);
+        todo_error.check_style();
+        assert_eq!(
+            todo_error.style,
+            vec!["missing space after comment delimiter"]
+        );
+        assert_eq!(
+            serde_json::to_value(&todo_error).unwrap()["language"],
+            "dsl"
+        );
+
+        let issues = TodoError::from_line(&config, Path::new("a.dsl"), "#| TODO(#2): y |#", 1);
+        assert_eq!(issues[0].message, "y");
+    }
+
+    #[test]
+    fn wrapped_issue_is_tracked() {
+        let config = regexes(r"\btodo:.*?issue #(?P<issue_number>\d+)", true);
+        let issues = from_comment(&config, "    // TODO: see\n    // issue #42 for details\n");
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].tracking_id.as_deref(), Some("42"));
+        assert_eq!((issues[0].row, issues[0].col), (1, 8));
+        assert_eq!(issues[0].message, "for details");
+        assert_eq!(
+            issues[0].help_message.as_deref(),
+            Some("link: https://example.com/issues/42")
+        );
+        assert_eq!(
+            issues[0].link.as_deref(),
+            Some("https://example.com/issues/42")
+        );
+    }
+
+    #[test]
+    fn wrapped_issue_is_opt_in() {
+        let config = regexes(r"\btodo:.*?issue #(?P<issue_number>\d+)", false);
+        let issues = from_comment(&config, "// TODO: see\n// issue #42\n");
+
+        assert_eq!(issues.len(), 1);
+        assert!(!issues[0].is_tracked());
+    }
+
+    #[test]
+    fn wrapped_match_must_start_on_keyword_line() {
+        let config = regexes(r"\btodo:.*?issue #(?P<issue_number>\d+)", true);
+        let issues = from_comment(&config, "// TODO\n// TODO: issue #42\n");
+
+        assert_eq!(issues.len(), 2);
+        assert!(!issues[0].is_tracked());
+        assert_eq!(issues[1].tracking_id.as_deref(), Some("42"));
+        assert_eq!(issues[1].row, 2);
+    }
+
+    #[test]
+    fn c_block_comment_delimiter_is_trimmed() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let line = "int x; /* TODO(#3): fix */";
+        let issues = TodoError::from_line(&config, Path::new("a.c"), line, 1);
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].message, "fix");
+        assert_eq!(issues[0].col, 11);
+        assert_eq!(issues[0].span_len, "TODO(#3): fix".len());
+    }
+
+    #[test]
+    fn block_comment_continuations() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        for fixture in &["c.c", "java.java", "shell.sh"] {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("test_cases")
+                .join(fixture);
+            let contents = std::fs::read_to_string(&path).unwrap();
+            let issues = (1..)
+                .zip(contents.lines())
+                .flat_map(|(row, line)| TodoError::from_line(&config, &path, line, row))
+                .collect::<Vec<_>>();
+
+            assert_eq!(issues.len(), if *fixture == "c.c" { 3 } else { 2 });
+            for issue in &issues {
+                // The column and underline start at the keyword, not the ` * ` or ` # ` prefix.
+                let from_keyword = &issue.original_line[issue.col - 1..];
+                assert!(from_keyword.starts_with("TODO"), "{}", issue.original_line);
+                assert_eq!(issue.span_len, from_keyword.trim().len());
+                assert!(!issue.message.starts_with(['*', '#']));
+            }
+        }
+    }
+
+    #[test]
+    fn html_comment_delimiter_is_trimmed() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let tracked = TodoError::from_line(
+            &config,
+            Path::new("a.html"),
+            "    <!-- TODO(#3): fix the layout -->",
+            1,
+        );
+        let untracked = TodoError::from_line(&config, Path::new("a.html"), "<!-- TODO fix -->", 2);
+
+        assert_eq!(tracked[0].message, "fix the layout");
+        assert_eq!(tracked[0].span_len, "TODO(#3): fix the layout".len());
+        assert_eq!(untracked[0].span_len, "TODO fix".len());
+    }
+
+    #[test]
+    fn only_the_languages_block_comment_delimiter_is_trimmed() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let message = |path: &str, line: &str| {
+            TodoError::from_line(&config, Path::new(path), line, 1)
+                .remove(0)
+                .message
+        };
+
+        assert_eq!(
+            message("a.py", "# TODO(#3): match a/**/*.c */"),
+            "match a/**/*.c */"
+        );
+        assert_eq!(message("a.sh", "# TODO(#3): print -->"), "print -->");
+        assert_eq!(message("a.html", "<!-- TODO(#3): fix */"), "fix */");
+        assert_eq!(message("a.hs", "{- TODO(#3): fix -}"), "fix");
+        assert_eq!(message("a.unknown", "/* TODO(#3): fix */"), "fix");
+    }
+
+    #[test]
+    fn prefilter() {
+        assert!(LiteralPrefilter::new(vec!["todo", "fix.*me"]).is_none());
+
+        let prefilter = LiteralPrefilter::new(vec!["todo", "fixme"]).unwrap();
+        assert!(prefilter.may_match("// TODO: a"));
+        assert!(prefilter.may_match("// t tOdO"));
+        assert!(prefilter.may_match("fixme"));
+        assert!(!prefilter.may_match("// nothing to see here"));
+        assert!(!prefilter.may_match("tod"));
+        assert!(prefilter.may_match("// \u{212a} is not ASCII"));
+    }
+
+    #[test]
+    fn prefiltered_lines_match_like_unfiltered() {
+        let mut config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        config.prefilter = LiteralPrefilter::new(vec!["todo"]);
+
+        assert!(TodoError::from_line(&config, Path::new("a.rs"), "let x = 1;", 1).is_empty());
+        assert!(TodoError::from_line(&config, Path::new("a.rs"), "let todos = 1;", 1).is_empty());
+        let issues = TodoError::from_line(&config, Path::new("a.rs"), "// TODO(#1): a", 1);
+        assert_eq!(issues[0].tracking_id.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn identity_ignores_position() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let identify = |path: &str, line: &str, row| {
+            let todo_error = &TodoError::from_line(&config, Path::new(path), line, row)[0];
+            (todo_error.id.clone(), todo_error.fingerprint.clone())
+        };
+
+        let original = identify("./a.rs", "// TODO: handle  errors", 3);
+        assert_eq!(
+            identify("./a.rs", "        // TODO: handle errors", 40),
+            original
+        );
+        assert_eq!(identify("a.rs", "x(); // TODO: handle errors", 3), original);
+        assert_ne!(
+            identify("./a.rs", "// TODO: handle all errors", 3).1,
+            original.1
+        );
+        assert_ne!(
+            identify("./b.rs", "// TODO: handle errors", 3).1,
+            original.1
+        );
+
+        let tracked = identify("./a.rs", "// TODO(#1): handle errors", 3);
+        assert_eq!(identify("./a.rs", "// TODO(#1): handle errors", 9), tracked);
+        assert_ne!(tracked.1, original.1);
+
+        let retracked = identify("./a.rs", "// TODO(#2): handle errors", 3);
+        assert_ne!(retracked.0, tracked.0);
+        assert_eq!(retracked.1, tracked.1);
+
+        // Pinned, so that changes to the hashing that would break consumers are noticed.
+        assert_eq!(
+            tracked,
+            ("322b61beeceef993".to_owned(), "76c9a867486d9cde".to_owned())
+        );
+    }
+
+    #[test]
+    fn rule_id_follows_keyword() {
+        let mut config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        config.warning_keywords = vec![regex::RegexBuilder::new(r"\bfixme\b")
+            .case_insensitive(true)
+            .build()
+            .unwrap()];
+
+        let untracked = TodoError::from_line(&config, Path::new("a.rs"), "// FIXME later", 1);
+        let tracked = TodoError::from_line(&config, Path::new("a.rs"), "// TODO(#1): a", 2);
+        let url = TodoError::from_line(
+            &config,
+            Path::new("a.rs"),
+            "// FixMe: https://example.com/issues/42",
+            3,
+        );
+
+        assert_eq!(untracked[0].rule_id, "report-todo/fixme/untracked");
+        assert_eq!(tracked[0].rule_id, "report-todo/todo/tracked");
+        assert_eq!(url[0].rule_id, "report-todo/fixme/tracked");
+    }
+
+    #[test]
+    fn first_match_only() {
+        let mut config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        config.warning_keywords = vec![regex::RegexBuilder::new(r"\bfixme\b")
+            .case_insensitive(true)
+            .build()
+            .unwrap()];
+        let line = "// FIXME: todo and todo";
+
+        let issues = TodoError::from_line(&config, Path::new("a.rs"), line, 1);
+        assert_eq!(issues.len(), 2);
+
+        config.first_match_only = true;
+        let issues = TodoError::from_line(&config, Path::new("a.rs"), line, 1);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].keyword, "fixme");
+        assert_eq!(issues[0].col, 4);
+    }
+
+    #[test]
+    fn short_snippet_is_whole_line() {
+        assert_eq!(
+            snippet_window("a // TODO b", 5, 6, 80),
+            ("a // TODO b".to_owned(), 5, 6)
+        );
+    }
+
+    #[test]
+    fn long_snippet_is_windowed() {
+        let line = format!("{}// TODO fix{}", "x".repeat(200_000), "y".repeat(1000));
+        let (shown, start, len) = snippet_window(&line, 200_003, 8, 40);
+
+        assert_eq!(shown.chars().count(), 40);
+        assert!(shown.starts_with('…') && shown.ends_with('…'));
+        assert_eq!(len, 8);
+        assert_eq!(
+            shown.chars().skip(start).take(len).collect::<String>(),
+            "TODO fix"
+        );
+    }
+
+    #[test]
+    fn rust_macros() {
+        let mut config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        config.rust_macros = Some(
+            RustMacros::new(vec![
+                "todo=error".parse().unwrap(),
+                "unimplemented=warning".parse().unwrap(),
+                "unreachable=ignore".parse().unwrap(),
+            ])
+            .unwrap(),
+        );
+        let from_line =
+            |path: &str, line: &str| TodoError::from_line(&config, Path::new(path), line, 1);
+
+        let todo = from_line("a.rs", "    todo!() // TODO: later");
+        assert_eq!(todo.len(), 2);
+        assert_eq!(todo[0].rule_id, "report-todo/todo-macro/untracked");
+        assert_eq!(todo[0].severity, Severity::Error);
+        assert_eq!(todo[0].col, 5);
+        assert_eq!(todo[1].rule_id, "report-todo/todo/untracked");
+        assert_eq!(todo[1].col, 16);
+
+        let unimplemented = from_line("a.rs", "unimplemented!(\"stub\")");
+        assert_eq!(unimplemented[0].severity, Severity::Warning);
+        assert!(from_line("a.rs", "unreachable!()").is_empty());
+
+        // Only in Rust files.
+        assert_eq!(
+            from_line("a.py", "todo!()")[0].rule_id,
+            "report-todo/todo/untracked"
+        );
+
+        // Not in string literals or comments.
+        let macro_cols = |line: &str| {
+            from_line("a.rs", line)
+                .iter()
+                .filter(|issue| issue.keyword.ends_with("-macro"))
+                .map(|issue| issue.col)
+                .collect::<Vec<_>>()
+        };
+        assert!(macro_cols(r#"let a = "todo!()";"#).is_empty());
+        assert!(macro_cols(r#"let a = "\"todo!()";"#).is_empty());
+        assert!(macro_cols(r##"let a = r#"x" todo!()"#;"##).is_empty());
+        assert!(macro_cols("// call todo!() later").is_empty());
+        assert_eq!(macro_cols("/* todo!() */ todo!()"), vec![15]);
+        assert_eq!(macro_cols(r#"let c = '"'; todo!()"#), vec![14]);
+        assert_eq!(macro_cols("fn f<'a>() -> &'a str { todo!() }"), vec![25]);
+    }
+
+    #[test]
+    fn rust_macro_span() {
+        let mut config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        config.rust_macros = Some(RustMacros::new(vec!["todo=error".parse().unwrap()]).unwrap());
+        let span = |line: &str| {
+            let issue = &TodoError::from_line(&config, Path::new("a.rs"), line, 1)[0];
+            line[issue.col - 1..][..issue.span_len].to_owned()
+        };
+
+        assert_eq!(span("    todo!() // TODO: later"), "todo!()");
+        assert_eq!(span(r#"let a = todo!("(", b) + 1;"#), r#"todo!("(", b)"#);
+        assert_eq!(span("todo! [1, [2]];"), "todo! [1, [2]]");
+        // Arguments continued on the next line.
+        assert_eq!(span("    todo!(  "), "todo!(");
+    }
+
+    #[test]
+    fn rust_macros_in_fixture() {
+        let mut config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        config.rust_macros = Some(RustMacros::new(vec!["todo=error".parse().unwrap()]).unwrap());
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_cases/rust.rs");
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let macros = (1..)
+            .zip(contents.lines())
+            .flat_map(|(row, line)| TodoError::from_line(&config, &path, line, row))
+            .filter(|issue| issue.keyword.ends_with("-macro"))
+            .collect::<Vec<_>>();
+
+        // `todo!("with message")`, `todo!   (format!(..))`, `todo!("\#1234: woo")` and `todo!()`,
+        // but not `unimplemented!()` on line 24.
+        assert_eq!(
+            macros.iter().map(|issue| issue.row).collect::<Vec<_>>(),
+            vec![6, 7, 23, 25]
+        );
+        for issue in &macros {
+            assert_eq!(issue.keyword, "todo-macro");
+            assert!(!issue.is_tracked());
+            assert_eq!(issue.severity, Severity::Error);
+            assert_eq!(issue.message, "`todo!()` macro invocation");
+            assert!(issue
+                .help_message
+                .as_deref()
+                .unwrap()
+                .contains("create a work item"));
+        }
+    }
+
+    #[test]
+    fn allow_marker_with_reason() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let issues = TodoError::from_line(
+            &config,
+            Path::new("a.rs"),
+            "// TODO fix later // report-todo:allow(no-issue-yet: waiting on design)",
+            1,
+        );
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].col, 4);
+        assert_eq!(
+            issues[0].suppression.as_deref(),
+            Some("no-issue-yet: waiting on design")
+        );
+    }
+
+    #[test]
+    fn allow_marker_without_reason() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let issues = TodoError::from_line(
+            &config,
+            Path::new("a.rs"),
+            "// TODO fix later // report-todo:allow",
+            1,
+        );
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].suppression.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn allow_marker_on_previous_line() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let suppression = |previous_line| {
+            let issues = TodoError::from_line_after(
+                &config,
+                Path::new("a.rs"),
+                Some(&Language::Rust),
+                previous_line,
+                "    // TODO fix later",
+                2,
+            );
+            assert_eq!(issues.len(), 1);
+            issues[0].suppression.clone()
+        };
+
+        assert_eq!(
+            suppression(Some("    // report-todo:allow(example)")).as_deref(),
+            Some("example")
+        );
+        assert_eq!(
+            suppression(Some("# Report-Todo:Allow")).as_deref(),
+            Some("")
+        );
+        assert_eq!(
+            suppression(Some("// report_todo:allow(underscore)")).as_deref(),
+            Some("underscore")
+        );
+        assert_eq!(suppression(Some("let a = 1; // report-todo:allow")), None);
+        assert_eq!(suppression(Some("// TODO x // report-todo:allow")), None);
+        assert_eq!(suppression(Some("// nothing to see")), None);
+        assert_eq!(suppression(None), None);
+    }
+
+    #[test]
+    fn allow_marker_does_not_match_keyword() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let issues = TodoError::from_line(
+            &config,
+            Path::new("a.rs"),
+            "let x = 1; // report-todo:allow",
+            1,
+        );
+
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn short_and_url_issue_references() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+
+        let short = TodoError::from_line(&config, Path::new("a.rs"), "// TODO(#42): fix", 1);
+        let url = TodoError::from_line(
+            &config,
+            Path::new("a.rs"),
+            "// TODO: https://example.com/issues/42",
+            2,
+        );
+
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].tracking_id.as_deref(), Some("42"));
+        assert_eq!(url.len(), 1);
+        assert_eq!(url[0].tracking_id.as_deref(), Some("42"));
+        assert_eq!(url[0].col, 4);
+        assert_eq!(url[0].message, "https://example.com/issues/42");
+        assert_eq!(
+            url[0].help_message.as_deref(),
+            Some("link: https://example.com/issues/42")
+        );
+    }
+
+    #[test]
+    fn url_without_keyword_is_ignored() {
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let issues = TodoError::from_line(
+            &config,
+            Path::new("a.rs"),
+            "// see https://example.com/issues/42",
+            1,
+        );
+
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn named_capture_groups() {
+        let mut config = regexes(
+            r"\btodo\((?P<project>[A-Z]+)-(?P<key>\d+), @(?P<owner>\w+)\):",
+            false,
+        );
+        config.groups.id = "key".to_owned();
+        config.groups.assignee = "owner".to_owned();
+
+        let issues = TodoError::from_line(
+            &config,
+            Path::new("a.rs"),
+            "// TODO(JIRA-456, @alice): refactor",
+            1,
+        );
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].tracking_id.as_deref(), Some("456"));
+        assert_eq!(issues[0].details.assignee.as_deref(), Some("alice"));
+        assert_eq!(issues[0].details.project.as_deref(), Some("JIRA"));
+        assert_eq!(issues[0].details.due, None);
+    }
+
+    #[test]
+    fn render_assignee() {
+        let config = regexes(
+            r"\btodo\(@?(?P<assignee>\w+), #(?P<issue_number>\d+)\):",
+            false,
+        );
+        let issues = TodoError::from_line(
+            &config,
+            Path::new("a.rs"),
+            "// TODO(@alice, #12): refactor",
+            1,
+        );
+        assert!(render(&issues)
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(" (assigned to @alice)"));
+
+        // Patterns without the group report no assignee.
+        let config = regexes(r"\btodo\(#(?P<issue_number>\d+)\):", false);
+        let issues = TodoError::from_line(&config, Path::new("a.rs"), "// TODO(#12): refactor", 1);
+        assert!(!render(&issues).contains("assigned to"));
+    }
+
+    #[test]
+    fn all_named_captures_are_kept() {
+        let config = regexes(
+            r"\btodo\((?P<team>[a-z]+)/(?P<issue_number>\d+)(?:, (?P<sprint>S\d+))?\):",
+            false,
+        );
+        let captures = |line| {
+            let issues = TodoError::from_line(&config, Path::new("a.rs"), line, 1);
+            serde_json::to_value(&issues[0]).unwrap()["captures"].clone()
+        };
+
+        assert_eq!(
+            captures("// TODO(web/12, S4): x"),
+            serde_json::json!({"issue_number": "12", "sprint": "S4", "team": "web"})
+        );
+        assert_eq!(
+            captures("// TODO(web/12): x"),
+            serde_json::json!({"issue_number": "12", "team": "web"})
+        );
+    }
+
+    #[test]
+    fn unnamed_capture_is_tracking_id() {
+        let config = regexes(r"\btodo\((\w+)\):", false);
+        let issues = TodoError::from_line(&config, Path::new("a.rs"), "// TODO(abc): x", 1);
+
+        assert_eq!(issues[0].tracking_id.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn severity_map() {
+        let entries = ["untracked=blocker".to_owned(), "tracked=note".to_owned()];
+        let severity_map = SeverityMap::parse(&entries).unwrap();
+        let mut untracked = TodoError::sample(false);
+        let mut tracked = TodoError::sample(true);
+        untracked.relabel(&severity_map, "2024-02-01");
+        tracked.relabel(&severity_map, "2024-02-01");
+
+        assert_eq!(untracked.severity_label, "blocker");
+        assert_eq!(tracked.severity_label, "note");
+        assert_eq!(severity_map.label(Severity::Warning), "warning");
+
+        let error = SeverityMap::parse(&["late=error".to_owned()]).unwrap_err();
+        assert!(error.to_string().contains("unknown state `late`"));
+        assert!(SeverityMap::parse(&["untracked".to_owned()]).is_err());
+        assert!(SeverityMap::parse(&["untracked=".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn overdue_severity() {
+        let config = regexes(
+            r"\btodo\(#(?P<issue_number>\d+)(?:, due (?P<due>[^)]+))?\):",
+            false,
+        );
+        let severity_map =
+            SeverityMap::parse(&["tracked=note".to_owned(), "overdue=error".to_owned()]).unwrap();
+        let label = |line: &str| {
+            let mut issue = TodoError::from_line(&config, Path::new("a.rs"), line, 1).remove(0);
+            issue.relabel(&severity_map, "2024-02-01");
+            issue.severity_label
+        };
+
+        assert_eq!(label("// TODO(#1, due 2024-01-31): overdue"), "error");
+        assert_eq!(label("// TODO(#1, due 2024-02-01): due today"), "note");
+        assert_eq!(label("// TODO(#1, due end of sprint): not a date"), "note");
+        assert_eq!(label("// TODO(#1): no due date"), "note");
+        assert!(is_date("2024-02-01"));
+        assert!(!is_date("2024-2-1"));
+    }
+}
@@ -0,0 +1,268 @@
+//! `--tui`: browsing findings in a terminal UI, for triaging them locally. Built with the `tui`
+//! feature.
+
+use std::{io::stdout, process::Command};
+
+use anyhow::{anyhow, Context};
+use ratatui::{
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEventKind},
+        terminal::{enable_raw_mode, EnterAlternateScreen},
+        ExecutableCommand,
+    },
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use crate::{paths::display_path, todo_error::TodoError};
+
+const HELP: &str = "↑/↓ select  o open issue  e/Enter edit  q quit";
+
+/// Shows `findings` grouped by file until the user quits. Arrow keys select a finding, `o` opens
+/// its issue link in the browser and `e` opens its file at its line in `$VISUAL` or `$EDITOR`.
+pub fn browse(findings: &[TodoError]) -> anyhow::Result<()> {
+    if findings.is_empty() {
+        return Ok(());
+    }
+
+    let mut browser = Browser::new(findings);
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+/// The findings being browsed and which one is selected.
+struct Browser {
+    /// Sorted by file, then by position, with their snippets embedded.
+    findings: Vec<TodoError>,
+    selected: usize,
+
+    /// The outcome of the last action, shown in place of the help.
+    status: Option<String>,
+}
+
+impl Browser {
+    fn new(findings: &[TodoError]) -> Self {
+        let mut findings = findings.to_vec();
+        findings.sort_by(|a, b| {
+            (a.file_path(), a.row(), a.col()).cmp(&(b.file_path(), b.row(), b.col()))
+        });
+        for todo_error in &mut findings {
+            todo_error.embed_snippet();
+        }
+        Browser {
+            findings,
+            selected: 0,
+            status: None,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            self.status = None;
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.select_previous(),
+                KeyCode::Home => self.selected = 0,
+                KeyCode::End => self.selected = self.findings.len() - 1,
+                KeyCode::Char('o') => {
+                    self.status = Some(match open_link(self.selected()) {
+                        Ok(link) => format!("opened {}", link),
+                        Err(error) => format!("{:#}", error),
+                    })
+                }
+                KeyCode::Char('e') | KeyCode::Enter => {
+                    // Hand the terminal over to the editor until it exits.
+                    ratatui::restore();
+                    let edited = edit(self.selected());
+                    enable_raw_mode()?;
+                    stdout().execute(EnterAlternateScreen)?;
+                    terminal.clear()?;
+                    if let Err(error) = edited {
+                        self.status = Some(format!("{:#}", error));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn selected(&self) -> &TodoError {
+        &self.findings[self.selected]
+    }
+
+    fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.findings.len() - 1);
+    }
+
+    fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// The list entries, a header per file followed by its findings, and the index of the
+    /// selected finding's entry.
+    fn items(&self) -> (Vec<ListItem<'_>>, usize) {
+        let mut items = Vec::new();
+        let mut selected = 0;
+        for (i, todo_error) in self.findings.iter().enumerate() {
+            if i == 0 || todo_error.file_path() != self.findings[i - 1].file_path() {
+                items.push(ListItem::new(display_path(todo_error.file_path())).bold());
+            }
+            if i == self.selected {
+                selected = items.len();
+            }
+            let location = format!("  {:>5}:{:<3} ", todo_error.row(), todo_error.col());
+            let message = if todo_error.is_tracked() {
+                todo_error.message().to_owned().into()
+            } else {
+                todo_error.message().to_owned().red()
+            };
+            items.push(ListItem::new(Line::from(vec![location.dim(), message])));
+        }
+        (items, selected)
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [list_area, snippet_area, help_area] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(4),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let (items, selected) = self.items();
+        let list = List::new(items).highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(
+            list,
+            list_area,
+            &mut ListState::default().with_selected(Some(selected)),
+        );
+
+        let todo_error = self.selected();
+        let mut lines = Vec::new();
+        if let Some(snippet) = todo_error.snippet() {
+            let (before, rest) = snippet.line.split_at(snippet.span_start);
+            let (span, after) = rest.split_at(snippet.span_len.min(rest.len()));
+            lines.push(Line::from(vec![
+                Span::raw(before),
+                Span::raw(span).yellow().bold(),
+                Span::raw(after),
+            ]));
+        }
+        lines.push(Line::from(todo_error.link().unwrap_or("untracked").dim()));
+        let title = format!(
+            " {}:{}:{} ",
+            display_path(todo_error.file_path()),
+            todo_error.row(),
+            todo_error.col()
+        );
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            snippet_area,
+        );
+
+        frame.render_widget(
+            Line::from(self.status.as_deref().unwrap_or(HELP).dim()),
+            help_area,
+        );
+    }
+}
+
+/// Opens `todo_error`'s issue link in the browser, returning the link.
+fn open_link(todo_error: &TodoError) -> anyhow::Result<&str> {
+    let link = todo_error
+        .link()
+        .ok_or_else(|| anyhow!("no issue link for this finding"))?;
+    open::that(link).with_context(|| format!("couldn't open {}", link))?;
+    Ok(link)
+}
+
+/// Opens `todo_error`'s file at its line in the user's editor and waits for it to exit.
+fn edit(todo_error: &TodoError) -> anyhow::Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_owned());
+    let args = editor_command(&editor, todo_error);
+    let status = Command::new(&args[0])
+        .args(&args[1..])
+        .status()
+        .with_context(|| format!("couldn't run {}", editor))?;
+    if !status.success() {
+        return Err(anyhow!("{} exited with {}", editor, status));
+    }
+    Ok(())
+}
+
+/// The command line opening `todo_error` in `editor`, which may include arguments, e.g. `emacs
+/// -nw`. Uses the `+LINE` argument that vi, Emacs and nano understand.
+fn editor_command(editor: &str, todo_error: &TodoError) -> Vec<String> {
+    editor
+        .split_whitespace()
+        .map(str::to_owned)
+        .chain(vec![
+            format!("+{}", todo_error.row()),
+            display_path(todo_error.file_path()),
+        ])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn finding(path: &str, tracked: bool) -> TodoError {
+        let mut todo_error = TodoError::sample(tracked);
+        todo_error.set_file_path(PathBuf::from(path));
+        todo_error
+    }
+
+    #[test]
+    fn grouped_by_file() {
+        let mut browser = Browser::new(&[
+            finding("b.rs", false),
+            finding("a.rs", true),
+            finding("b.rs", true),
+        ]);
+
+        let paths = browser
+            .findings
+            .iter()
+            .map(|todo_error| display_path(todo_error.file_path()))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["a.rs", "b.rs", "b.rs"]);
+        // Headers for `a.rs` and `b.rs`.
+        assert_eq!(browser.items().0.len(), 5);
+        assert_eq!(browser.items().1, 1);
+
+        browser.select_next();
+        assert_eq!(browser.items().1, 3);
+        browser.select_next();
+        browser.select_next();
+        assert_eq!(browser.items().1, 4);
+        browser.select_previous();
+        assert_eq!(browser.selected().file_path(), PathBuf::from("b.rs"));
+        assert!(browser.selected().snippet().is_some());
+    }
+
+    #[test]
+    fn editor_at_line() {
+        assert_eq!(
+            editor_command("emacs -nw", &finding("src/a.rs", false)),
+            ["emacs", "-nw", "+2", "src/a.rs"]
+        );
+    }
+}
@@ -0,0 +1,8 @@
+/*
+ * TODO(#3): fix the block comment continuation
+ */
+int x;
+
+/* TODO(#4): on the opening line
+ * TODO: untracked on a continuation line
+ */
@@ -0,0 +1,22 @@
+using System;
+
+class Program
+{
+    /// <summary>
+    /// TODO: in an XML doc comment
+    /// </summary>
+    static void Main()
+    {
+        var a = @"todo: in a verbatim string // not a comment";
+        var b = @"todo: in a ""quoted"" verbatim string";
+        var c = $"todo: in an interpolated string {a} // not a comment";
+        var d = $@"todo: in an interpolated verbatim string {b}";
+
+        // TODO line comment
+        // TODO(#1234): line comment
+
+        /* toDO in block comment
+           toDO(#235): in block comment */
+        Console.WriteLine(a + b + c + d);
+    }
+}
@@ -0,0 +1,10 @@
+class A {
+    /**
+     * TODO(#5): in a Javadoc continuation
+     *
+     * @return TODO: untracked in a Javadoc tag
+     */
+    int a() {
+        return 0;
+    }
+}
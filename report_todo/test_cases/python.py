@@ -0,0 +1,8 @@
+#!/usr/bin/env python3
+# TODO(#6): python comment
+URL = "https://example.com/todo"
+message = "TODO in a string"
+
+
+def f():
+    pass  # TODO: untracked python comment
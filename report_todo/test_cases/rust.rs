@@ -22,8 +22,15 @@ fn main() {
 
     todo!("\#1234: woo");
     unimplemented!();
+    todo!();
 
     let a = "todo(#1234): inside a string literal";
+    let a = r"todo: inside a raw string literal";
+    let a = r#"todo: inside a "raw" string literal"#;
+    let a = r##"todo: inside a "#raw"# string literal"##;
+
+    /* outer /* todo: in a nested block comment */ still the outer comment */
+    let a = 1; /* /* */ */ let a = "todo: after a nested block comment";
 
 /*  
 toDO in block comment
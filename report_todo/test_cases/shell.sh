@@ -0,0 +1,5 @@
+#!/bin/sh
+: <<'COMMENT'
+ # TODO(#6): in a heredoc block comment
+ # TODO: untracked in a heredoc block comment
+COMMENT
@@ -0,0 +1,5 @@
+int main(void) {
+    int x = 0; // TODO(#8): clean comment
+	return x; /* TODO(#9): indented with a tab */
+    // TODO(#10): trailing whitespace   
+}